@@ -17,16 +17,55 @@
 #include <stdint.h>
 #include <stdlib.h>
 
+// The password is never part of the 'json_cfg' config, which is rejected if it has a
+// "password" field. It's passed separately, to the calls which open the wallet, and
+// to 'grin_wallet_open' only for the 'grin_session_*' calls, and it's never kept.
+// The revealing of the mnemonic always requires the password.
+
+// On failure, '*error' is set to 1 and the returned string is a JSON error object:
+// {"code": 2001, "category": "auth", "message": "...", "causes": ["..."]}
+// 'code' is stable, 'category' is one of: auth, network, funds, slate, storage, config, internal.
+
 void cstr_free(const char *s);
 
+// The wrong passwords are counted in the wallet data directory. After 3 of them in a row,
+// each one doubles the delay before the next attempt, from 5 seconds up to one hour, and
+// the calls checking the password fail with the error 2004 until it's elapsed. If
+// "wipe_after_failures" of the config is set, at least 5, the wallet data directory is
+// wiped after that many wrong passwords in a row, and the calls fail with the error 2005.
+// A right password, or a wallet init or recovery, resets the count.
+// "wipe_after_failures" is saved with the count by 'grin_wallet_init',
+// 'grin_wallet_init_recover' and 'grin_wallet_open' with the right password, and it's
+// ignored in the config of the other calls.
 const char*  grin_check_password(
     const char* json_cfg,
     const char* password,
     uint8_t *error
 );
 
+// Returns {"failures": 4, "retryDelaySecs": 7, "attemptsBeforeWipe": 6}, the last one
+// being null if the wallet is never wiped
+const char* grin_password_attempts(
+    const char* json_cfg,
+    uint8_t *error
+);
+
+const char* grin_session_password_attempts(
+    uint64_t handle,
+    uint8_t *error
+);
+
 const char* grin_init_wallet_seed(uint8_t *error);
 
+// Returns {"valid": false, "words": [{"index": 0, "valid": true}, ...], "wordCount": 12,
+// "expectedLength": 12, "checksumValid": false}, "checksumValid" being null while a word
+// is wrong or the word count isn't 12, 15, 18, 21 or 24. The words themselves are never
+// returned, "index" is the position of a word in the phrase, from 0.
+const char* grin_mnemonic_validate(const char* phrase, uint8_t *error);
+
+// Returns the words of the BIP-39 wordlist starting with the prefix, as a JSON array
+const char* grin_mnemonic_suggest(const char* prefix, uint8_t *error);
+
 const char* grin_wallet_init(
      const char* json_cfg,
      const char* password,
@@ -34,14 +73,18 @@ const char* grin_wallet_init(
      uint8_t *error
 );
 
+// The words of 'mnemonic' are lowercased and separated by single spaces, as they're
+// validated by 'grin_mnemonic_validate'.
 const char* grin_wallet_init_recover(
     const char* json_cfg,
     const char* mnemonic,
+    const char* password,
     uint8_t *error
 );
 
 const char* grin_wallet_restore(
     const char* json_cfg,
+    const char* password,
     uint64_t start_index,
     uint64_t batch_size,
     uint8_t *error
@@ -49,6 +92,7 @@ const char* grin_wallet_restore(
 
 const char* grin_wallet_check(
     const char* json_cfg,
+    const char* password,
     uint64_t start_index,
     uint64_t batch_size,
     bool update_outputs,
@@ -57,88 +101,808 @@ const char* grin_wallet_check(
 
 const char* grin_get_wallet_mnemonic(
     const char* json_cfg,
+    const char* password,
     uint8_t *error
 );
 
 const char* grin_get_balance(
     const char* json_cfg,
+    const char* password,
     uint8_t *error
 );
 
 const char* grin_tx_retrieve(
     const char* json_cfg,
+    const char* password,
     const char* tx_slate_id,
     uint8_t *error
 );
 
+// Each transaction has its local metadata in its "metadata" field, null if none is set,
+// see 'grin_tx_metadata_set'.
 const char* grin_txs_retrieve(
     const char* json_cfg,
+    const char* password,
+    uint8_t *error
+);
+
+// Query a page of the transactions. 'filter_json' can be NULL, for all the transactions,
+// or a JSON object of optional fields:
+// {"offset": 0, "limit": 50, "tx_type": "sent|received|cancelled|coinbase",
+//  "confirmed": true, "from_date": 1568000000, "to_date": 1569000000,
+//  "min_amount": 1000000000, "max_amount": 5000000000, "order": "asc|desc"}
+// The dates are the Unix timestamps of the creation, in seconds, and the amount is
+// the credited amount minus the debited amount, in absolute value. The bounds are
+// included. The transactions are sorted by creation time, the newest first by default.
+// Returns {"validated": true, "total": 123, "txs": [...]}, where "total" is the number
+// of the transactions matching the filters, of all the pages.
+const char* grin_txs_query(
+    const char* json_cfg,
+    const char* password,
+    const char* filter_json,
     uint8_t *error
 );
 
 const char* grin_output_retrieve(
     const char* json_cfg,
+    const char* password,
     uint32_t id,
     uint8_t *error
 );
 
 const char* grin_outputs_retrieve(
     const char* json_cfg,
+    const char* password,
+    uint8_t *error
+);
+
+// Frozen outputs are never selected to fund a send, unless they are selected by
+// "input_commits". 'grin_outputs_frozen' returns the array of their commitments.
+const char* grin_outputs_frozen(
+    const char* json_cfg,
+    const char* password,
+    uint8_t *error
+);
+
+const char* grin_output_freeze(
+    const char* json_cfg,
+    const char* password,
+    const char* commit,
+    bool frozen,
     uint8_t *error
 );
 
 const char* grin_listen(
     const char* json_cfg,
+    const char* password,
     uint8_t *error
 );
 
+// 'send_options' is a JSON object of optional coin selection parameters, NULL meaning
+// the defaults: {"minimum_confirmations": 10, "max_outputs": 500,
+// "num_change_outputs": 1, "src_acct_name": "..."}
+// The default 'minimum_confirmations' is the one of the wallet config, in [1, 10080].
+// "input_commits": ["..."] selects the outputs to spend, instead of the coin selection:
+// they must be spendable outputs of the source account, otherwise the error code is 4003.
+// 'src_acct_name' is the account to send from, instead of the active account.
 const char* grin_init_tx(
     const char* json_cfg,
+    const char* password,
     uint64_t amount,
     const char* selection_strategy,
     int16_t target_slate_version,
     const char* message,
+    const char* send_options,
+    uint8_t *error
+);
+
+// Estimate the fee of a send, without creating a transaction.
+// 'selection_strategy' is "all" or "smallest", NULL meaning both.
+// Returns a JSON array, one estimate per strategy:
+// [{"selectionStrategy": "smallest", "amount": 0, "fee": 0, "total": 0,
+//   "inputs": 0, "outputs": 0, "locked": 0, "change": 0}]
+// where 'total' is the amount plus the fee, and 'locked' the value of the inputs.
+// With "input_commits" of 'send_options' or frozen outputs, the other outputs are marked
+// locked in the wallet database for the time of the estimate, as for a send, so the
+// session must not be locked even if "read_when_locked" is set.
+const char* grin_estimate_fee(
+    const char* json_cfg,
+    const char* password,
+    uint64_t amount,
+    const char* selection_strategy,
+    const char* send_options,
+    uint8_t *error
+);
+
+// Spend the outputs to the wallet itself: all into one output, or split into
+// 'num_outputs' outputs of about the same value, in [2, 33], to send in parallel.
+// The smallest spendable outputs are spent, up to "max_outputs" of 'send_options'.
+// Returns the posted slate, or with 'estimate_only' the fee estimate as a JSON object,
+// in the format of 'grin_estimate_fee'.
+// The outputs of the active account are spent, "src_acct_name" is an argument error.
+const char* grin_consolidate_outputs(
+    const char* json_cfg,
+    const char* password,
+    bool estimate_only,
+    const char* send_options,
     uint8_t *error
 );
 
+const char* grin_split_outputs(
+    const char* json_cfg,
+    const char* password,
+    uint32_t num_outputs,
+    bool estimate_only,
+    const char* send_options,
+    uint8_t *error
+);
+
+// Send to a wallet url (http:// or https://), or to a Grin Relay address.
+// A relay send returns the slate id once the wallet listener sent the slate, the send
+// being cancelled if it can't be sent. The listener then finalizes and posts the payee's
+// response in background, reporting the progress by the "slateSent", "sendFinalized"
+// and "sendFailed" events. The listener is started if it isn't running yet.
+// Only a response from the payee's address is accepted. A response which can't be
+// finalized leaves the send pending, for the right response or 'grin_cancel_tx', and a
+// finalized transaction which can't be posted can be posted again by 'grin_post_tx'.
 const char* grin_send_tx(
     const char* json_cfg,
+    const char* password,
     uint64_t amount,
     const char* receiver_wallet_url,
     const char* selection_strategy,
     int16_t target_slate_version,
     const char* message,
+    const char* send_options,
     uint8_t *error
 );
 
 const char* grin_cancel_tx(
     const char* json_cfg,
+    const char* password,
     const char* tx_slate_id,
     uint8_t *error
 );
 
 const char* grin_post_tx(
     const char* json_cfg,
+    const char* password,
     const char* tx_slate_id,
     uint8_t *error
 );
 
+// Build a slate, write it to 'slate_file_path' and lock the outputs.
+// Returns the slate.
+const char* grin_tx_file_send(
+    const char* json_cfg,
+    const char* password,
+    uint64_t amount,
+    const char* selection_strategy,
+    int16_t target_slate_version,
+    const char* message,
+    const char* slate_file_path,
+    const char* send_options,
+    uint8_t *error
+);
+
 const char* grin_tx_file_receive(
     const char* json_cfg,
+    const char* password,
+    const char* slate_file_path,
+    const char* message,
+    uint8_t *error
+);
+
+// Receive a slate file, and write the signed slate to 'response_file_path'.
+const char* grin_tx_file_respond(
+    const char* json_cfg,
+    const char* password,
     const char* slate_file_path,
     const char* message,
+    const char* response_file_path,
     uint8_t *error
 );
 
 const char*  grin_tx_file_finalize(
     const char* json_cfg,
+    const char* password,
     const char* slate_file_path,
     uint8_t *error
 );
 
 const char* grin_chain_height(
     const char* json_cfg,
+    const char* password,
+    uint8_t *error
+);
+
+// Wallet session API.
+// The wallet is opened once by 'grin_wallet_open', which returns a handle,
+// and all the 'grin_session_*' calls on this handle share the opened wallet.
+// The 'account' argument, when there is one, selects the account for this call only,
+// NULL meaning the account of the session config.
+// A wallet is only opened once: opening an already opened wallet again checks the
+// password and returns the same handle, whose account is the one of the first open.
+// 'grin_wallet_close' also stops the listeners of the wallet.
+
+// The optional passphrase, the BIP-39 "25th word", can be NULL or empty for none. The
+// same recovery phrase with another passphrase opens another wallet, and a wrong
+// passphrase just opens an empty wallet. It's never stored, only the recovery phrase is,
+// so 'grin_get_wallet_mnemonic' shows the same phrase for all the passphrases. The
+// database, transaction metadata, payment proofs, invoices and frozen outputs of the
+// wallet of a passphrase are kept in its own directory of the data directory, named by
+// its root key id, so that none of them is seen from another passphrase; only the
+// password attempts are shared. The wallet of a passphrase is only opened by this call,
+// the calls taking 'json_cfg' always open the wallet without passphrase. An open wallet
+// must be closed before it's opened with another passphrase.
+const char* grin_wallet_open(
+    const char* json_cfg,
+    const char* password,
+    const char* passphrase,
+    uint8_t *error
+);
+
+const char* grin_wallet_close(
+    uint64_t handle,
+    uint8_t *error
+);
+
+// A session is locked by 'grin_wallet_lock', or when it's idle for "lock_timeout_secs"
+// of its config, if set. Only the calls which spend, reveal secrets or change the wallet
+// count as activity, the read-only ones below don't. The calls on a locked session fail with the error 2003, until
+// 'grin_wallet_unlock' with the wallet password. If "read_when_locked" of the config is
+// true, the read-only calls still work: the balance, the transactions, the outputs, the
+// accounts list, the fee estimate without coin control, the payment proofs, the
+// transaction metadata reading, the export, the received invoices and the chain height.
+// The listener keeps running.
+const char* grin_wallet_lock(
+    uint64_t handle,
+    uint8_t *error
+);
+
+const char* grin_wallet_unlock(
+    uint64_t handle,
+    const char* password,
+    uint8_t *error
+);
+
+// Returns true if the session is locked
+const char* grin_wallet_is_locked(
+    uint64_t handle,
+    uint8_t *error
+);
+
+// Returns a JSON array of the accounts: [{"label": "default", "path": "..."}]
+const char* grin_accounts_list(
+    const char* json_cfg,
+    const char* password,
+    uint8_t *error
+);
+
+const char* grin_session_accounts_list(
+    uint64_t handle,
     uint8_t *error
 );
 
+// Returns the created account: {"label": "...", "path": "..."}
+const char* grin_account_create(
+    const char* json_cfg,
+    const char* password,
+    const char* label,
+    uint8_t *error
+);
+
+const char* grin_session_account_create(
+    uint64_t handle,
+    const char* label,
+    uint8_t *error
+);
+
+// The session is re-opened with the new password, and its listeners are restarted with
+// the same ids. If that fails, the password is changed anyway, the handle is closed and
+// the error code is 2006: the wallet must be opened again by 'grin_wallet_open'.
+const char* grin_session_change_password(
+    uint64_t handle,
+    const char* old_password,
+    const char* new_password,
+    uint8_t *error
+);
+
+const char* grin_session_restore(
+    uint64_t handle,
+    uint64_t start_index,
+    uint64_t batch_size,
+    uint8_t *error
+);
+
+const char* grin_session_check(
+    uint64_t handle,
+    uint64_t start_index,
+    uint64_t batch_size,
+    bool update_outputs,
+    uint8_t *error
+);
+
+const char* grin_session_get_wallet_mnemonic(
+    uint64_t handle,
+    const char* password,
+    uint8_t *error
+);
+
+const char* grin_session_get_balance(
+    uint64_t handle,
+    const char* account,
+    uint8_t *error
+);
+
+const char* grin_session_tx_retrieve(
+    uint64_t handle,
+    const char* tx_slate_id,
+    uint8_t *error
+);
+
+const char* grin_session_txs_retrieve(
+    uint64_t handle,
+    uint8_t *error
+);
+
+const char* grin_session_txs_query(
+    uint64_t handle,
+    const char* filter_json,
+    uint8_t *error
+);
+
+const char* grin_session_output_retrieve(
+    uint64_t handle,
+    uint32_t id,
+    uint8_t *error
+);
 
+const char* grin_session_outputs_retrieve(
+    uint64_t handle,
+    uint8_t *error
+);
+
+const char* grin_session_outputs_frozen(
+    uint64_t handle,
+    uint8_t *error
+);
+
+const char* grin_session_output_freeze(
+    uint64_t handle,
+    const char* commit,
+    bool frozen,
+    uint8_t *error
+);
+
+const char* grin_session_listen(
+    uint64_t handle,
+    uint8_t *error
+);
+
+const char* grin_session_init_tx(
+    uint64_t handle,
+    const char* account,
+    uint64_t amount,
+    const char* selection_strategy,
+    int16_t target_slate_version,
+    const char* message,
+    const char* send_options,
+    uint8_t *error
+);
+
+const char* grin_session_estimate_fee(
+    uint64_t handle,
+    const char* account,
+    uint64_t amount,
+    const char* selection_strategy,
+    const char* send_options,
+    uint8_t *error
+);
+
+const char* grin_session_consolidate_outputs(
+    uint64_t handle,
+    const char* account,
+    bool estimate_only,
+    const char* send_options,
+    uint8_t *error
+);
+
+const char* grin_session_split_outputs(
+    uint64_t handle,
+    const char* account,
+    uint32_t num_outputs,
+    bool estimate_only,
+    const char* send_options,
+    uint8_t *error
+);
+
+const char* grin_session_send_tx(
+    uint64_t handle,
+    const char* account,
+    uint64_t amount,
+    const char* receiver_wallet_url,
+    const char* selection_strategy,
+    int16_t target_slate_version,
+    const char* message,
+    const char* send_options,
+    uint8_t *error
+);
+
+const char* grin_session_cancel_tx(
+    uint64_t handle,
+    const char* tx_slate_id,
+    uint8_t *error
+);
+
+const char* grin_session_post_tx(
+    uint64_t handle,
+    const char* tx_slate_id,
+    uint8_t *error
+);
+
+const char* grin_session_tx_file_send(
+    uint64_t handle,
+    const char* account,
+    uint64_t amount,
+    const char* selection_strategy,
+    int16_t target_slate_version,
+    const char* message,
+    const char* slate_file_path,
+    const char* send_options,
+    uint8_t *error
+);
+
+const char* grin_session_tx_file_receive(
+    uint64_t handle,
+    const char* account,
+    const char* slate_file_path,
+    const char* message,
+    uint8_t *error
+);
+
+const char* grin_session_tx_file_respond(
+    uint64_t handle,
+    const char* account,
+    const char* slate_file_path,
+    const char* message,
+    const char* response_file_path,
+    uint8_t *error
+);
+
+const char* grin_session_tx_file_finalize(
+    uint64_t handle,
+    const char* slate_file_path,
+    uint8_t *error
+);
+
+const char* grin_session_chain_height(
+    uint64_t handle,
+    uint8_t *error
+);
+
+// Grin Relay listener API.
+// 'grin_session_listen_start' returns the listener id, or the id of the listener already
+// running for the session. A listener only handles the transactions of the wallet it was
+// started for, so a passphrase wallet needs the listener of its own session, and each
+// 'grin_listen_start' starts a new listener. 'grin_listen_status' returns a JSON object:
+// {"id": 1, "state": "connecting|connected|stopped", "relayAddr": "...",
+//  "lastError": "...", "slatesReceived": 0}
+
+const char* grin_listen_start(
+    const char* json_cfg,
+    const char* password,
+    uint8_t *error
+);
+
+const char* grin_session_listen_start(
+    uint64_t handle,
+    uint8_t *error
+);
+
+const char* grin_listen_stop(
+    uint64_t listener_id,
+    uint8_t *error
+);
+
+const char* grin_listen_status(
+    uint64_t listener_id,
+    uint8_t *error
+);
+
+// Wallet events.
+// Returns a JSON array of the events queued since the last poll, the oldest firstly:
+// [{"type": "slateReceived|slateResponded|respondFailed|verificationFailed|"
+//            "slateSent|sendFinalized|sendFailed|"
+//            "invoiceReceived|invoiceFinalized|invoiceFailed",
+//   "listenerId": 1, "slateId": "...", "addr": "...", "amount": 0,
+//   "message": "...", "error": "...", "timestamp": 0}]
+const char* grin_events_poll(uint8_t *error);
+
+// Invoice API, for the payee to request a payment.
+// 'grin_invoice_issue' returns the invoice slate, to be sent to the payer as JSON or
+// file, or published by Grin Relay to 'payer_addr' when it isn't NULL. The listener
+// then finalizes and posts the payer's response, reporting the "invoiceFinalized" or
+// "invoiceFailed" event.
+// The payer pays an invoice only if its amount is 'approved_amount' and its fee is
+// at most 'max_fee', otherwise the error code is 5008 and no output is locked.
+// An invoice received by Grin Relay is reported by the "invoiceReceived" event, and
+// kept until it's paid by 'grin_invoice_pay' or rejected by 'grin_invoice_reject'.
+// 'grin_invoices_received' returns the invoices waiting for the user's approval:
+// [{"addr": "...", "slate": {...}, "receivedAt": 0}]
+
+const char* grin_invoice_issue(
+    const char* json_cfg,
+    const char* password,
+    uint64_t amount,
+    int16_t target_slate_version,
+    const char* message,
+    const char* payer_addr,
+    uint8_t *error
+);
+
+const char* grin_session_invoice_issue(
+    uint64_t handle,
+    const char* account,
+    uint64_t amount,
+    int16_t target_slate_version,
+    const char* message,
+    const char* payer_addr,
+    uint8_t *error
+);
+
+const char* grin_invoice_process(
+    const char* json_cfg,
+    const char* password,
+    const char* slate_json,
+    uint64_t approved_amount,
+    uint64_t max_fee,
+    const char* selection_strategy,
+    const char* message,
+    const char* send_options,
+    uint8_t *error
+);
+
+const char* grin_session_invoice_process(
+    uint64_t handle,
+    const char* account,
+    const char* slate_json,
+    uint64_t approved_amount,
+    uint64_t max_fee,
+    const char* selection_strategy,
+    const char* message,
+    const char* send_options,
+    uint8_t *error
+);
+
+const char* grin_invoice_file_process(
+    const char* json_cfg,
+    const char* password,
+    const char* slate_file_path,
+    const char* response_file_path,
+    uint64_t approved_amount,
+    uint64_t max_fee,
+    const char* selection_strategy,
+    const char* message,
+    const char* send_options,
+    uint8_t *error
+);
+
+const char* grin_session_invoice_file_process(
+    uint64_t handle,
+    const char* account,
+    const char* slate_file_path,
+    const char* response_file_path,
+    uint64_t approved_amount,
+    uint64_t max_fee,
+    const char* selection_strategy,
+    const char* message,
+    const char* send_options,
+    uint8_t *error
+);
+
+const char* grin_invoices_received(
+    const char* json_cfg,
+    const char* password,
+    uint8_t *error
+);
+
+const char* grin_session_invoices_received(
+    uint64_t handle,
+    uint8_t *error
+);
+
+const char* grin_invoice_pay(
+    const char* json_cfg,
+    const char* password,
+    const char* tx_slate_id,
+    uint64_t approved_amount,
+    uint64_t max_fee,
+    const char* selection_strategy,
+    const char* message,
+    const char* send_options,
+    uint8_t *error
+);
+
+const char* grin_session_invoice_pay(
+    uint64_t handle,
+    const char* account,
+    const char* tx_slate_id,
+    uint64_t approved_amount,
+    uint64_t max_fee,
+    const char* selection_strategy,
+    const char* message,
+    const char* send_options,
+    uint8_t *error
+);
+
+const char* grin_invoice_reject(
+    const char* json_cfg,
+    const char* password,
+    const char* tx_slate_id,
+    uint8_t *error
+);
+
+const char* grin_session_invoice_reject(
+    uint64_t handle,
+    const char* tx_slate_id,
+    uint8_t *error
+);
+
+const char* grin_invoice_finalize(
+    const char* json_cfg,
+    const char* password,
+    const char* slate_json,
+    uint8_t *error
+);
+
+const char* grin_session_invoice_finalize(
+    uint64_t handle,
+    const char* slate_json,
+    uint8_t *error
+);
+
+const char* grin_invoice_file_finalize(
+    const char* json_cfg,
+    const char* password,
+    const char* slate_file_path,
+    uint8_t *error
+);
+
+const char* grin_session_invoice_file_finalize(
+    uint64_t handle,
+    const char* slate_file_path,
+    uint8_t *error
+);
+
+// Payment proof API.
+// When paid by Grin Relay, the listener of a payee signs a payment proof over the
+// amount, the kernel excess and the relay addresses of the payer and of the payee.
+// The payer keeps it with its tx log once the transaction is finalized, if the payee
+// sent one signed for the relay address it was paid at. There is no payment proof
+// for a transaction sent to a wallet url, whose payee never signs one.
+// A payment proof is a JSON object:
+// {"slateId": "...", "amount": 1000000000, "excess": "08...", "senderAddress": "...",
+//  "receiverAddress": "02...", "receiverRelayAddress": "...", "receiverSignature": "...",
+//  "createdAt": 1568000000}
+// The "receiverAddress" is the payment proof address of the payee, which it gets
+// by 'grin_payment_proof_address'.
+
+// Returns the payment proof of a sent transaction, or the error 5009 if there is none.
+const char* grin_tx_payment_proof(
+    const char* json_cfg,
+    const char* password,
+    const char* tx_slate_id,
+    uint8_t *error
+);
+
+const char* grin_session_tx_payment_proof(
+    uint64_t handle,
+    const char* tx_slate_id,
+    uint8_t *error
+);
+
+// Verify the signature of a payment proof, failing with the error 5010 if it's wrong,
+// then look for its kernel on the chain, failing with the error 3001 if the node
+// can't be asked. Returns:
+// {"confirmed": true, "kernelHeight": 123456, "confirmations": 10}
+const char* grin_verify_payment_proof(
+    const char* json_cfg,
+    const char* password,
+    const char* proof_json,
+    uint8_t *error
+);
+
+const char* grin_session_verify_payment_proof(
+    uint64_t handle,
+    const char* proof_json,
+    uint8_t *error
+);
+
+const char* grin_payment_proof_address(
+    const char* json_cfg,
+    const char* password,
+    uint8_t *error
+);
+
+const char* grin_session_payment_proof_address(
+    uint64_t handle,
+    uint8_t *error
+);
+
+// Transaction metadata API.
+// A transaction can have local metadata, which is never sent to the counterparty:
+// {"note": "...", "tags": ["..."], "counterparty": "..."}
+// All the fields are optional. The note is at most 1000 bytes. The counterparty is set
+// to the relay address or the url of 'grin_send_tx', unless the user set one.
+
+// Replace the metadata of a transaction, an empty object removing it.
+const char* grin_tx_metadata_set(
+    const char* json_cfg,
+    const char* password,
+    const char* tx_slate_id,
+    const char* metadata_json,
+    uint8_t *error
+);
+
+const char* grin_session_tx_metadata_set(
+    uint64_t handle,
+    const char* tx_slate_id,
+    const char* metadata_json,
+    uint8_t *error
+);
+
+const char* grin_tx_metadata_get(
+    const char* json_cfg,
+    const char* password,
+    const char* tx_slate_id,
+    uint8_t *error
+);
+
+const char* grin_session_tx_metadata_get(
+    uint64_t handle,
+    const char* tx_slate_id,
+    uint8_t *error
+);
+
+// Search the metadata whose note, counterparty or one of the tags contains 'text',
+// case insensitively. Returns a JSON array of the metadata objects, each with its
+// "txSlateId" field.
+const char* grin_tx_metadata_search(
+    const char* json_cfg,
+    const char* password,
+    const char* text,
+    uint8_t *error
+);
+
+const char* grin_session_tx_metadata_search(
+    uint64_t handle,
+    const char* text,
+    uint8_t *error
+);
+
+// Export the transactions to a report file at 'path', the oldest first. 'format' is
+// "csv" or "json". 'date_range' can be NULL, for all the transactions, or a JSON object
+// {"from_date": 1568000000, "to_date": 1569000000}, both optional, with the Unix
+// timestamps of the creation, in seconds, included. Each transaction has the fields
+// date, direction (sent|received|coinbase), amount, amount_nanogrin, fee, fee_nanogrin,
+// status (confirmed|unconfirmed|cancelled), kernel_excess, slate_id, note, tags and
+// counterparty. The amounts are in GRIN with 9 decimals and in nanogrin, and a sent
+// amount doesn't include the fee. The JSON fields are in camelCase, and the CSV tags
+// are separated by ';'. Returns the number of the exported transactions.
+const char* grin_export_txs(
+    const char* json_cfg,
+    const char* password,
+    const char* format,
+    const char* path,
+    const char* date_range,
+    uint8_t *error
+);
+
+const char* grin_session_export_txs(
+    uint64_t handle,
+    const char* format,
+    const char* path,
+    const char* date_range,
+    uint8_t *error
+);
@@ -0,0 +1,281 @@
+// Copyright 2019 Gary Yu.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rate limiting of the wallet password attempts, against brute force.
+//!
+//! The wrong passwords are counted in the wallet data directory, so that the count
+//! survives a restart of the app. After a few free attempts, each wrong password
+//! doubles the delay before the next attempt, and the wallet data can be wiped
+//! after too many wrong passwords, if the config says so.
+//!
+//! The wipe policy is saved with the count, from the config of the calls which know
+//! the password: the wallet init and recovery, and the session open. The config of the
+//! other calls can't change it, nor be used to wipe a wallet.
+
+use std::collections::HashSet;
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use grin_wallet_impls::{Error, ErrorKind, WalletSeed};
+use grin_wallet_util::grin_util::Mutex;
+
+use crate::error::LibError;
+use crate::events::unix_timestamp;
+use crate::store;
+use crate::MobileWalletCfg;
+
+/// Kept with the seed file whose password it counts, even for a passphrase wallet
+const PASSWORD_ATTEMPTS_FILE: &str = "password_attempts.json";
+
+/// Wrong passwords allowed without any delay
+pub const FREE_ATTEMPTS: u32 = 3;
+/// Delay after the first delayed wrong password, doubled by each next one
+pub const BASE_DELAY_SECS: u64 = 5;
+/// Maximum delay between two attempts, i.e. one hour
+pub const MAX_DELAY_SECS: u64 = 3600;
+/// Minimum of the `wipe_after_failures` config, so that a few typos never wipe a wallet
+pub const MIN_WIPE_AFTER_FAILURES: u32 = 5;
+
+/// The persisted count of the wrong passwords
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+struct AttemptsState {
+    /// Consecutive wrong passwords, reset by a right one
+    failures: u32,
+    /// Unix timestamp of the last wrong password, in seconds
+    last_failure: u64,
+    /// Wrong passwords in a row after which the wallet data is wiped, none meaning never
+    #[serde(default)]
+    wipe_after_failures: Option<u32>,
+}
+
+impl AttemptsState {
+    /// Seconds to wait before the next attempt is allowed
+    fn retry_delay(&self) -> u64 {
+        let delay = failure_delay(self.failures);
+        let now = unix_timestamp();
+        if now < self.last_failure {
+            // The clock went back, don't let it shorten the delay
+            return delay;
+        }
+        (self.last_failure + delay).saturating_sub(now).min(delay)
+    }
+}
+
+/// The delay imposed after a number of consecutive wrong passwords
+fn failure_delay(failures: u32) -> u64 {
+    if failures < FREE_ATTEMPTS {
+        return 0;
+    }
+    let shift = (failures - FREE_ATTEMPTS).min(32);
+    BASE_DELAY_SECS
+        .checked_shl(shift)
+        .unwrap_or(MAX_DELAY_SECS)
+        .min(MAX_DELAY_SECS)
+}
+
+/// The password attempts state, as returned to the app
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordAttempts {
+    /// Consecutive wrong passwords
+    pub failures: u32,
+    /// Seconds to wait before the next attempt is allowed
+    pub retry_delay_secs: u64,
+    /// Wrong passwords left before the wallet data is wiped, none if it's never wiped
+    pub attempts_before_wipe: Option<u32>,
+}
+
+lazy_static! {
+    // Held during a whole password check, so that concurrent attempts can't bypass the count
+    static ref ATTEMPTS_LOCK: Mutex<()> = Mutex::new(());
+    // Data directories wiped since the library was loaded, whose sessions must not be used
+    static ref WIPED: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+fn load(config: &MobileWalletCfg) -> Result<AttemptsState, Error> {
+    store::load(&store::wallet_data_dir(config).join(PASSWORD_ATTEMPTS_FILE))
+}
+
+fn save(config: &MobileWalletCfg, state: &AttemptsState) -> Result<(), Error> {
+    store::save(
+        &store::wallet_data_dir(config).join(PASSWORD_ATTEMPTS_FILE),
+        state,
+    )
+}
+
+fn wiped_error() -> Error {
+    LibError::WalletWiped.into_error("wallet data wiped after too many wrong passwords".to_owned())
+}
+
+/// Check the config of the wipe policy
+pub fn check_wipe_after_failures(wipe_after_failures: Option<u32>) -> Result<(), Error> {
+    match wipe_after_failures {
+        Some(n) if n < MIN_WIPE_AFTER_FAILURES => {
+            Err(Error::from(ErrorKind::ArgumentError(format!(
+                "wipe_after_failures {} is less than {}",
+                n, MIN_WIPE_AFTER_FAILURES
+            ))))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Whether the wallet data of this directory was wiped
+pub fn is_wiped(data_dir: &str) -> bool {
+    WIPED.lock().contains(data_dir)
+}
+
+/// Check the wallet password, which fails without checking it if the delay after
+/// the last wrong password isn't elapsed yet.
+///
+/// A wrong password is counted, and wipes the wallet data if it's one too many.
+pub fn verify_password(config: &MobileWalletCfg, password: &str) -> Result<(), Error> {
+    if is_wiped(&config.data_dir) {
+        return Err(wiped_error());
+    }
+    let _guard = ATTEMPTS_LOCK.lock();
+    let mut state = load(config)?;
+    let delay = state.retry_delay();
+    if delay > 0 {
+        return Err(LibError::TooManyPasswordAttempts.into_error(format!(
+            "too many wrong passwords, retry in {} seconds",
+            delay
+        )));
+    }
+
+    let data_file_dir = store::wallet_data_dir(config);
+    match WalletSeed::from_file(&data_file_dir.to_string_lossy(), password) {
+        Ok(_) => {
+            if state.failures > 0 {
+                state.failures = 0;
+                state.last_failure = 0;
+                save(config, &state)?;
+            }
+            Ok(())
+        }
+        Err(e) => {
+            match e.kind() {
+                ErrorKind::WalletSeedDecryption => {}
+                _ => return Err(e),
+            }
+            state.failures += 1;
+            state.last_failure = unix_timestamp();
+            if state
+                .wipe_after_failures
+                .map_or(false, |n| state.failures >= n)
+            {
+                WIPED.lock().insert(config.data_dir.clone());
+                fs::remove_dir_all(&data_file_dir).map_err(|e| {
+                    LibError::Io.into_error(format!(
+                        "fail to wipe {}: {}",
+                        data_file_dir.display(),
+                        e
+                    ))
+                })?;
+                return Err(wiped_error());
+            }
+            save(config, &state)?;
+            Err(e)
+        }
+    }
+}
+
+/// The password attempts state of a wallet
+pub fn password_attempts(config: &MobileWalletCfg) -> Result<PasswordAttempts, Error> {
+    if is_wiped(&config.data_dir) {
+        return Err(wiped_error());
+    }
+    let _guard = ATTEMPTS_LOCK.lock();
+    let state = load(config)?;
+    Ok(PasswordAttempts {
+        failures: state.failures,
+        retry_delay_secs: state.retry_delay(),
+        attempts_before_wipe: state
+            .wipe_after_failures
+            .map(|n| n.saturating_sub(state.failures)),
+    })
+}
+
+/// Forget the wrong passwords of a wallet, once it's created or recovered again, and
+/// save the wipe policy of its config.
+pub fn reset_password_attempts(config: &MobileWalletCfg) -> Result<(), Error> {
+    let _guard = ATTEMPTS_LOCK.lock();
+    WIPED.lock().remove(&config.data_dir);
+    save(
+        config,
+        &AttemptsState {
+            wipe_after_failures: config.wipe_after_failures,
+            ..AttemptsState::default()
+        },
+    )
+}
+
+/// Save the wipe policy of the config, once the wallet password is verified.
+pub fn save_wipe_policy(config: &MobileWalletCfg) -> Result<(), Error> {
+    let _guard = ATTEMPTS_LOCK.lock();
+    let mut state = load(config)?;
+    if state.wipe_after_failures != config.wipe_after_failures {
+        state.wipe_after_failures = config.wipe_after_failures;
+        save(config, &state)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(failures: u32, last_failure: u64) -> AttemptsState {
+        AttemptsState {
+            failures,
+            last_failure,
+            wipe_after_failures: None,
+        }
+    }
+
+    #[test]
+    fn delays_double_after_the_free_attempts() {
+        assert_eq!(failure_delay(0), 0);
+        assert_eq!(failure_delay(FREE_ATTEMPTS - 1), 0);
+        assert_eq!(failure_delay(FREE_ATTEMPTS), BASE_DELAY_SECS);
+        assert_eq!(failure_delay(FREE_ATTEMPTS + 1), BASE_DELAY_SECS * 2);
+        assert_eq!(failure_delay(FREE_ATTEMPTS + 3), BASE_DELAY_SECS * 8);
+    }
+
+    #[test]
+    fn delays_are_capped() {
+        assert_eq!(failure_delay(FREE_ATTEMPTS + 10), MAX_DELAY_SECS);
+        assert_eq!(failure_delay(FREE_ATTEMPTS + 40), MAX_DELAY_SECS);
+        assert_eq!(failure_delay(u32::max_value()), MAX_DELAY_SECS);
+    }
+
+    #[test]
+    fn retry_delay_counts_from_the_last_failure() {
+        let now = unix_timestamp();
+        assert_eq!(state(0, now).retry_delay(), 0);
+        let delay = state(FREE_ATTEMPTS, now).retry_delay();
+        assert!(delay > 0 && delay <= BASE_DELAY_SECS);
+        assert_eq!(state(FREE_ATTEMPTS, now - BASE_DELAY_SECS).retry_delay(), 0);
+    }
+
+    #[test]
+    fn retry_delay_is_not_shortened_by_a_clock_going_back() {
+        let later = unix_timestamp() + 3600 * 24;
+        assert_eq!(
+            state(FREE_ATTEMPTS + 1, later).retry_delay(),
+            BASE_DELAY_SECS * 2
+        );
+    }
+}
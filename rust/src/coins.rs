@@ -0,0 +1,251 @@
+// Copyright 2019 Gary Yu.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Manual coin control: frozen outputs, and sends from selected outputs.
+//!
+//! The wallet has no way to exclude outputs from its coin selection, so the outputs
+//! which must not be selected are marked as locked while a transaction is built,
+//! then unlocked. They are recorded beforehand, so that a crash in between never
+//! leaves them locked: they are unlocked when the wallet is opened again.
+
+use std::collections::BTreeSet;
+
+use grin_wallet_api::Owner;
+use grin_wallet_impls::{Error, ErrorKind, HTTPNodeClient};
+use grin_wallet_libwallet::{OutputData, OutputStatus, WalletBackend, WalletInst};
+use grin_wallet_util::grin_keychain::{ExtKeychain, Identifier};
+use grin_wallet_util::grin_util::Mutex;
+
+use crate::error::LibError;
+use crate::session::WalletInstance;
+use crate::store;
+use crate::MobileWalletCfg;
+
+const FROZEN_OUTPUTS_FILE: &str = "frozen_outputs.json";
+const EXCLUDED_OUTPUTS_FILE: &str = "excluded_outputs.json";
+
+lazy_static! {
+    // Serialize the coin selections, and the read-modify-write of the frozen outputs file
+    static ref COINS_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// The commitments of the frozen outputs, which are never selected automatically
+pub fn frozen_outputs(config: &MobileWalletCfg) -> Result<BTreeSet<String>, Error> {
+    store::load(&store::wallet_data_file(config, FROZEN_OUTPUTS_FILE))
+}
+
+/// Freeze or unfreeze an unspent output of the wallet
+pub fn set_frozen(
+    config: &MobileWalletCfg,
+    wallet: &WalletInstance,
+    commit: &str,
+    frozen: bool,
+) -> Result<(), Error> {
+    let _guard = COINS_LOCK.lock();
+    let path = store::wallet_data_file(config, FROZEN_OUTPUTS_FILE);
+    let mut commits: BTreeSet<String> = store::load(&path)?;
+    if frozen {
+        let output = find_output(wallet, commit)?;
+        if output.status == OutputStatus::Spent {
+            return Err(
+                LibError::OutputNotSpendable.into_error(format!("output {} is spent", commit))
+            );
+        }
+        commits.insert(commit.to_owned());
+    } else {
+        commits.remove(commit);
+    }
+    store::save(&path, &commits)
+}
+
+fn find_output(wallet: &WalletInstance, commit: &str) -> Result<OutputData, Error> {
+    let w = wallet.lock();
+    let output = w
+        .iter()
+        .find(|o| o.commit.as_ref().map(|c| c.as_str()) == Some(commit));
+    output.ok_or_else(|| {
+        Error::from(ErrorKind::ArgumentError(format!(
+            "unknown output: {}",
+            commit
+        )))
+    })
+}
+
+/// Build a transaction by `f` with coin control.
+///
+/// If `input_commits` is given, only these outputs can be selected, and they must be
+/// spendable outputs of the account `src_acct_name`, or else of the active account.
+/// Otherwise, all the outputs but the frozen ones can be selected. The outputs of the
+/// other accounts are left alone, they can't be selected anyway.
+///
+/// The coin selections are serialized, so `f` must lock the outputs it selects before
+/// it returns, for them not to be selected again by a concurrent transaction.
+pub fn with_coin_control<T, F>(
+    config: &MobileWalletCfg,
+    wallet: &WalletInstance,
+    input_commits: Option<&[String]>,
+    src_acct_name: Option<&str>,
+    minimum_confirmations: u64,
+    f: F,
+) -> Result<T, Error>
+where
+    F: FnOnce() -> Result<T, Error>,
+{
+    let _guard = COINS_LOCK.lock();
+
+    if let Some(input_commits) = input_commits {
+        check_inputs(wallet, input_commits, src_acct_name, minimum_confirmations)?;
+    }
+    let frozen = frozen_outputs(config)?;
+    let mut w = wallet.lock();
+    let parent_key_id = source_parent_key_id(&mut *w, src_acct_name)?;
+    let excluded: Vec<String> = w
+        .iter()
+        .filter(|o| o.status == OutputStatus::Unspent && o.root_key_id == parent_key_id)
+        .filter_map(|o| o.commit)
+        .filter(|c| match input_commits {
+            Some(input_commits) => !input_commits.contains(c),
+            None => frozen.contains(c),
+        })
+        .collect();
+    drop(w);
+    if excluded.is_empty() {
+        return f();
+    }
+
+    // Recorded firstly, to be unlocked if we crash before the end
+    let path = store::wallet_data_file(config, EXCLUDED_OUTPUTS_FILE);
+    store::save(&path, &excluded)?;
+    let res = set_status(
+        wallet,
+        &excluded,
+        OutputStatus::Unspent,
+        OutputStatus::Locked,
+    )
+    .and_then(|_| f());
+    let restored = set_status(
+        wallet,
+        &excluded,
+        OutputStatus::Locked,
+        OutputStatus::Unspent,
+    )
+    .and_then(|_| store::save(&path, &Vec::<String>::new()));
+    // The result of `f` wins, a transaction whose outputs are locked must not be lost.
+    // If the excluded outputs can't be unlocked now, they're still recorded, and they
+    // are unlocked when the wallet is opened again.
+    let value = res?;
+    let _ = restored;
+    Ok(value)
+}
+
+/// Unlock the outputs left locked by a coin selection which didn't complete
+pub fn restore_excluded_outputs(
+    config: &MobileWalletCfg,
+    wallet: &WalletInstance,
+) -> Result<(), Error> {
+    let _guard = COINS_LOCK.lock();
+    let path = store::wallet_data_file(config, EXCLUDED_OUTPUTS_FILE);
+    let excluded: Vec<String> = store::load(&path)?;
+    if !excluded.is_empty() {
+        set_status(
+            wallet,
+            &excluded,
+            OutputStatus::Locked,
+            OutputStatus::Unspent,
+        )?;
+        store::save(&path, &Vec::<String>::new())?;
+    }
+    Ok(())
+}
+
+/// The parent key of the account `src_acct_name`, or else of the active account
+fn source_parent_key_id(
+    w: &mut dyn WalletInst<HTTPNodeClient, ExtKeychain>,
+    src_acct_name: Option<&str>,
+) -> Result<Identifier, Error> {
+    match src_acct_name {
+        Some(name) => w
+            .acct_path_iter()
+            .find(|a| a.label == name)
+            .map(|a| a.path)
+            .ok_or_else(|| {
+                Error::from(ErrorKind::ArgumentError(format!(
+                    "unknown account: {}",
+                    name
+                )))
+            }),
+        None => Ok(w.parent_key_id()),
+    }
+}
+
+fn check_inputs(
+    wallet: &WalletInstance,
+    input_commits: &[String],
+    src_acct_name: Option<&str>,
+    minimum_confirmations: u64,
+) -> Result<(), Error> {
+    if input_commits.is_empty() {
+        return Err(Error::from(ErrorKind::ArgumentError(
+            "input_commits is empty".to_owned(),
+        )));
+    }
+    let height = Owner::new(wallet.clone()).node_height()?.height;
+
+    let mut w = wallet.lock();
+    let parent_key_id = source_parent_key_id(&mut *w, src_acct_name)?;
+    for commit in input_commits {
+        let output = w
+            .iter()
+            .find(|o| o.commit.as_ref() == Some(commit))
+            .ok_or_else(|| {
+                Error::from(ErrorKind::ArgumentError(format!(
+                    "unknown output: {}",
+                    commit
+                )))
+            })?;
+        if output.root_key_id != parent_key_id {
+            return Err(LibError::OutputNotSpendable
+                .into_error(format!("output {} is not in the source account", commit)));
+        }
+        if !output.eligible_to_spend(height, minimum_confirmations) {
+            return Err(LibError::OutputNotSpendable.into_error(format!(
+                "output {} is not spendable, its status is {}",
+                commit, output.status
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Set the status of the outputs of some commitments, if their status is `from`
+fn set_status(
+    wallet: &WalletInstance,
+    commits: &[String],
+    from: OutputStatus,
+    to: OutputStatus,
+) -> Result<(), Error> {
+    let mut w = wallet.lock();
+    let outputs: Vec<OutputData> = w
+        .iter()
+        .filter(|o| o.status == from)
+        .filter(|o| o.commit.as_ref().map_or(false, |c| commits.contains(c)))
+        .collect();
+    let mut batch = w.batch()?;
+    for mut output in outputs {
+        output.status = to.clone();
+        batch.save(output)?;
+    }
+    batch.commit()?;
+    Ok(())
+}
@@ -0,0 +1,303 @@
+// Copyright 2019 Gary Yu.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Machine-readable errors returned across the C ABI.
+//!
+//! An error is returned as a JSON object:
+//! `{"code": 2001, "category": "auth", "message": "...", "causes": ["..."]}`.
+//! The codes are stable, the app can rely on them to localize and react to failures.
+
+use failure::Fail;
+use serde::Serialize;
+
+use grin_wallet_impls::{Error, ErrorKind};
+use grin_wallet_libwallet::ErrorKind as LibWalletErrorKind;
+
+/// Error category, for the app to decide how to react to an error
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorCategory {
+    Auth,
+    Network,
+    Funds,
+    Slate,
+    Storage,
+    Config,
+    Internal,
+}
+
+// Config errors
+pub const CODE_INVALID_ARGUMENT: u32 = 1001;
+pub const CODE_WALLET_SEED_EXISTS: u32 = 1002;
+pub const CODE_WALLET_SEED_NOT_EXISTS: u32 = 1003;
+pub const CODE_INVALID_MNEMONIC: u32 = 1004;
+pub const CODE_UNKNOWN_ACCOUNT: u32 = 1005;
+pub const CODE_ACCOUNT_EXISTS: u32 = 1006;
+
+// Auth errors
+pub const CODE_WRONG_PASSWORD: u32 = 2001;
+pub const CODE_ENCRYPTION: u32 = 2002;
+pub const CODE_WALLET_LOCKED: u32 = 2003;
+pub const CODE_TOO_MANY_PASSWORD_ATTEMPTS: u32 = 2004;
+pub const CODE_WALLET_WIPED: u32 = 2005;
+pub const CODE_SESSION_CLOSED: u32 = 2006;
+
+// Network errors
+pub const CODE_NODE_UNREACHABLE: u32 = 3001;
+pub const CODE_RELAY_NOT_CONNECTED: u32 = 3002;
+
+// Funds errors
+pub const CODE_NOT_ENOUGH_FUNDS: u32 = 4001;
+pub const CODE_FEE: u32 = 4002;
+pub const CODE_OUTPUT_NOT_SPENDABLE: u32 = 4003;
+
+// Slate and transaction errors
+pub const CODE_TX_NOT_EXISTS: u32 = 5001;
+pub const CODE_TX_ALREADY_RECEIVED: u32 = 5002;
+pub const CODE_TX_NOT_CANCELLABLE: u32 = 5003;
+pub const CODE_SIGNATURE: u32 = 5004;
+pub const CODE_SLATE_VERSION: u32 = 5005;
+pub const CODE_SLATE_INCOMPATIBLE: u32 = 5006;
+pub const CODE_TX_BUILD: u32 = 5007;
+pub const CODE_INVOICE_NOT_APPROVED: u32 = 5008;
+pub const CODE_PAYMENT_PROOF_NOT_FOUND: u32 = 5009;
+pub const CODE_PAYMENT_PROOF_INVALID: u32 = 5010;
+
+// Storage errors
+pub const CODE_IO: u32 = 6001;
+pub const CODE_FORMAT: u32 = 6002;
+pub const CODE_BACKEND: u32 = 6003;
+
+// Internal errors
+pub const CODE_GENERIC: u32 = 9001;
+pub const CODE_KEYCHAIN: u32 = 9002;
+pub const CODE_PANIC: u32 = 9003;
+
+/// Errors raised by this library itself, which have no wallet error kind.
+///
+/// They are carried as the cause of a wallet error, see `LibError::into_error`.
+#[derive(Clone, Copy, Debug, Fail, PartialEq)]
+pub enum LibError {
+    #[fail(display = "Grin Relay not connected")]
+    RelayNotConnected,
+    #[fail(display = "Invoice not approved")]
+    InvoiceNotApproved,
+    #[fail(display = "Output not spendable")]
+    OutputNotSpendable,
+    #[fail(display = "Payment proof not found")]
+    PaymentProofNotFound,
+    #[fail(display = "Payment proof invalid")]
+    PaymentProofInvalid,
+    #[fail(display = "Wallet locked")]
+    WalletLocked,
+    #[fail(display = "Too many password attempts")]
+    TooManyPasswordAttempts,
+    #[fail(display = "Wallet wiped")]
+    WalletWiped,
+    #[fail(display = "Session closed")]
+    SessionClosed,
+    #[fail(display = "IO error")]
+    Io,
+    #[fail(display = "Transaction doesn't exist")]
+    TxNotExists,
+}
+
+impl LibError {
+    /// Convert into a wallet error, with this error as the cause
+    pub fn into_error(self, message: String) -> Error {
+        Error::from(self.context(ErrorKind::GenericError(message)))
+    }
+
+    fn classify(self) -> (u32, ErrorCategory) {
+        match self {
+            LibError::RelayNotConnected => (CODE_RELAY_NOT_CONNECTED, ErrorCategory::Network),
+            LibError::InvoiceNotApproved => (CODE_INVOICE_NOT_APPROVED, ErrorCategory::Slate),
+            LibError::OutputNotSpendable => (CODE_OUTPUT_NOT_SPENDABLE, ErrorCategory::Funds),
+            LibError::PaymentProofNotFound => (CODE_PAYMENT_PROOF_NOT_FOUND, ErrorCategory::Slate),
+            LibError::PaymentProofInvalid => (CODE_PAYMENT_PROOF_INVALID, ErrorCategory::Slate),
+            LibError::WalletLocked => (CODE_WALLET_LOCKED, ErrorCategory::Auth),
+            LibError::TooManyPasswordAttempts => {
+                (CODE_TOO_MANY_PASSWORD_ATTEMPTS, ErrorCategory::Auth)
+            }
+            LibError::WalletWiped => (CODE_WALLET_WIPED, ErrorCategory::Auth),
+            LibError::SessionClosed => (CODE_SESSION_CLOSED, ErrorCategory::Auth),
+            LibError::Io => (CODE_IO, ErrorCategory::Storage),
+            LibError::TxNotExists => (CODE_TX_NOT_EXISTS, ErrorCategory::Slate),
+        }
+    }
+}
+
+/// The JSON error object returned to the app
+#[derive(Serialize, Debug)]
+pub struct ErrorEnvelope {
+    pub code: u32,
+    pub category: ErrorCategory,
+    pub message: String,
+    pub causes: Vec<String>,
+}
+
+impl ErrorEnvelope {
+    pub fn new(code: u32, category: ErrorCategory, message: &str) -> Self {
+        ErrorEnvelope {
+            code,
+            category,
+            message: message.to_owned(),
+            causes: vec![],
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| {
+            format!(
+                "{{\"code\":{},\"category\":\"internal\",\"message\":\"\",\"causes\":[]}}",
+                self.code
+            )
+        })
+    }
+}
+
+impl From<&Error> for ErrorEnvelope {
+    fn from(e: &Error) -> Self {
+        let (code, category) = Fail::iter_causes(e)
+            .find_map(|c| c.downcast_ref::<LibError>())
+            .map(|c| c.classify())
+            .unwrap_or_else(|| classify(&e.kind()));
+        ErrorEnvelope {
+            code,
+            category,
+            message: format!("{}", e),
+            causes: Fail::iter_causes(e).map(|c| format!("{}", c)).collect(),
+        }
+    }
+}
+
+fn classify(kind: &ErrorKind) -> (u32, ErrorCategory) {
+    match kind {
+        ErrorKind::LibWallet(kind, _) => classify_libwallet(kind),
+        ErrorKind::LibTX(_) => (CODE_TX_BUILD, ErrorCategory::Slate),
+        ErrorKind::Keychain(_) => (CODE_KEYCHAIN, ErrorCategory::Internal),
+        ErrorKind::IO { .. } => (CODE_IO, ErrorCategory::Storage),
+        ErrorKind::Format { .. } => (CODE_FORMAT, ErrorCategory::Storage),
+        ErrorKind::WalletSeedExists(_) => (CODE_WALLET_SEED_EXISTS, ErrorCategory::Config),
+        ErrorKind::WalletSeedDoesntExist => (CODE_WALLET_SEED_NOT_EXISTS, ErrorCategory::Config),
+        ErrorKind::WalletSeedDecryption => (CODE_WRONG_PASSWORD, ErrorCategory::Auth),
+        ErrorKind::Encryption => (CODE_ENCRYPTION, ErrorCategory::Auth),
+        ErrorKind::Mnemonic => (CODE_INVALID_MNEMONIC, ErrorCategory::Config),
+        ErrorKind::ArgumentError(_) => (CODE_INVALID_ARGUMENT, ErrorCategory::Config),
+        _ => (CODE_GENERIC, ErrorCategory::Internal),
+    }
+}
+
+fn classify_libwallet(kind: &LibWalletErrorKind) -> (u32, ErrorCategory) {
+    match kind {
+        LibWalletErrorKind::NotEnoughFunds { .. } => (CODE_NOT_ENOUGH_FUNDS, ErrorCategory::Funds),
+        LibWalletErrorKind::Fee(_) => (CODE_FEE, ErrorCategory::Funds),
+        LibWalletErrorKind::ClientCallback(_) => (CODE_NODE_UNREACHABLE, ErrorCategory::Network),
+        LibWalletErrorKind::Backend(_) => (CODE_BACKEND, ErrorCategory::Storage),
+        LibWalletErrorKind::IO { .. } => (CODE_IO, ErrorCategory::Storage),
+        LibWalletErrorKind::Format(_) => (CODE_FORMAT, ErrorCategory::Storage),
+        LibWalletErrorKind::TransactionDoesntExist(_) => (CODE_TX_NOT_EXISTS, ErrorCategory::Slate),
+        LibWalletErrorKind::TransactionAlreadyReceived(_) => {
+            (CODE_TX_ALREADY_RECEIVED, ErrorCategory::Slate)
+        }
+        LibWalletErrorKind::TransactionNotCancellable(_) => {
+            (CODE_TX_NOT_CANCELLABLE, ErrorCategory::Slate)
+        }
+        LibWalletErrorKind::Signature(_) => (CODE_SIGNATURE, ErrorCategory::Slate),
+        LibWalletErrorKind::SlateVersion(_) => (CODE_SLATE_VERSION, ErrorCategory::Slate),
+        LibWalletErrorKind::Compatibility(_) => (CODE_SLATE_INCOMPATIBLE, ErrorCategory::Slate),
+        LibWalletErrorKind::LibTX(_) | LibWalletErrorKind::Transaction(_) => {
+            (CODE_TX_BUILD, ErrorCategory::Slate)
+        }
+        LibWalletErrorKind::UnknownAccountLabel(_) => (CODE_UNKNOWN_ACCOUNT, ErrorCategory::Config),
+        LibWalletErrorKind::AccountLabelAlreadyExists(_) => {
+            (CODE_ACCOUNT_EXISTS, ErrorCategory::Config)
+        }
+        LibWalletErrorKind::Keychain(_) | LibWalletErrorKind::Secp(_) => {
+            (CODE_KEYCHAIN, ErrorCategory::Internal)
+        }
+        _ => (CODE_GENERIC, ErrorCategory::Internal),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grin_wallet_libwallet::Error as LibWalletError;
+
+    fn envelope(e: Error) -> (u32, ErrorCategory) {
+        let envelope = ErrorEnvelope::from(&e);
+        (envelope.code, envelope.category)
+    }
+
+    #[test]
+    fn classifies_the_wallet_errors() {
+        assert_eq!(
+            envelope(Error::from(ErrorKind::WalletSeedDecryption)),
+            (CODE_WRONG_PASSWORD, ErrorCategory::Auth)
+        );
+        assert_eq!(
+            envelope(Error::from(ErrorKind::ArgumentError("bad".to_owned()))),
+            (CODE_INVALID_ARGUMENT, ErrorCategory::Config)
+        );
+        assert_eq!(
+            envelope(Error::from(ErrorKind::GenericError("oops".to_owned()))),
+            (CODE_GENERIC, ErrorCategory::Internal)
+        );
+    }
+
+    #[test]
+    fn classifies_the_libwallet_errors() {
+        let libwallet = |kind| envelope(Error::from(LibWalletError::from(kind)));
+        assert_eq!(
+            libwallet(LibWalletErrorKind::TransactionDoesntExist("tx".to_owned())),
+            (CODE_TX_NOT_EXISTS, ErrorCategory::Slate)
+        );
+        assert_eq!(
+            libwallet(LibWalletErrorKind::ClientCallback("node".to_owned())),
+            (CODE_NODE_UNREACHABLE, ErrorCategory::Network)
+        );
+        assert_eq!(
+            libwallet(LibWalletErrorKind::UnknownAccountLabel("a".to_owned())),
+            (CODE_UNKNOWN_ACCOUNT, ErrorCategory::Config)
+        );
+    }
+
+    #[test]
+    fn classifies_the_library_errors_by_their_cause() {
+        assert_eq!(
+            envelope(LibError::Io.into_error("fail to write".to_owned())),
+            (CODE_IO, ErrorCategory::Storage)
+        );
+        assert_eq!(
+            envelope(LibError::TxNotExists.into_error("not found".to_owned())),
+            (CODE_TX_NOT_EXISTS, ErrorCategory::Slate)
+        );
+        assert_eq!(
+            envelope(LibError::WalletLocked.into_error("locked".to_owned())),
+            (CODE_WALLET_LOCKED, ErrorCategory::Auth)
+        );
+    }
+
+    #[test]
+    fn serializes_the_envelope() {
+        let e = LibError::SessionClosed.into_error("closed".to_owned());
+        let json: serde_json::Value =
+            serde_json::from_str(&ErrorEnvelope::from(&e).to_json()).unwrap();
+        assert_eq!(json["code"], CODE_SESSION_CLOSED);
+        assert_eq!(json["category"], "auth");
+        assert!(json["message"].is_string());
+        assert!(json["causes"].is_array());
+    }
+}
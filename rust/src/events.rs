@@ -0,0 +1,122 @@
+// Copyright 2019 Gary Yu.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Wallet events queue, polled by the app to learn what happened in the background.
+
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use grin_wallet_libwallet::Slate;
+use grin_wallet_util::grin_util::Mutex;
+
+/// Maximum number of events kept when the app doesn't poll them,
+/// the oldest ones are dropped firstly.
+const MAX_QUEUED_EVENTS: usize = 1000;
+
+/// Type of a wallet event
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum EventType {
+    /// A slate is received from a sender
+    SlateReceived,
+    /// The received slate is signed and sent back to the sender
+    SlateResponded,
+    /// The received slate can't be signed or sent back
+    RespondFailed,
+    /// The messages of the received slate fail to verify
+    VerificationFailed,
+    /// Our slate is sent to the payee
+    SlateSent,
+    /// The payee's response is finalized and the transaction posted
+    SendFinalized,
+    /// Our slate can't be sent, or the payee's response can't be finalized
+    SendFailed,
+    /// An invoice is received, waiting for the user's approval
+    InvoiceReceived,
+    /// The payer's response of our invoice is finalized and the transaction posted
+    InvoiceFinalized,
+    /// The payer's response of our invoice can't be finalized
+    InvoiceFailed,
+}
+
+/// A wallet event, as reported to the app
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+    #[serde(rename = "type")]
+    pub event_type: EventType,
+    pub listener_id: u64,
+    pub slate_id: String,
+    /// The relay address of the counterparty
+    pub addr: String,
+    pub amount: u64,
+    pub message: Option<String>,
+    pub error: Option<String>,
+    /// Unix timestamp, in seconds
+    pub timestamp: u64,
+}
+
+impl Event {
+    pub fn new(event_type: EventType, listener_id: u64, addr: &str, slate: &Slate) -> Self {
+        Event {
+            event_type,
+            listener_id,
+            slate_id: slate.id.to_string(),
+            addr: addr.to_owned(),
+            amount: slate.amount,
+            // The message of the payer, or of the payee for an invoice
+            message: slate
+                .participant_data
+                .iter()
+                .find(|p| p.id == 0)
+                .or_else(|| slate.participant_data.first())
+                .and_then(|p| p.message.clone()),
+            error: None,
+            timestamp: unix_timestamp(),
+        }
+    }
+
+    pub fn with_error(mut self, error: String) -> Self {
+        self.error = Some(error);
+        self
+    }
+}
+
+/// Current Unix timestamp, in seconds
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+lazy_static! {
+    static ref EVENTS: Mutex<VecDeque<Event>> = Mutex::new(VecDeque::new());
+}
+
+/// Queue an event for the app
+pub fn emit(event: Event) {
+    let mut events = EVENTS.lock();
+    if events.len() >= MAX_QUEUED_EVENTS {
+        events.pop_front();
+    }
+    events.push_back(event);
+}
+
+/// Take all the queued events, the oldest firstly.
+pub fn poll_events() -> Vec<Event> {
+    EVENTS.lock().drain(..).collect()
+}
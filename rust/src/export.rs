@@ -0,0 +1,274 @@
+// Copyright 2019 Gary Yu.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Transaction history reports, in CSV or JSON.
+//!
+//! The records are written one by one to a temporary file, which is renamed
+//! once complete, so that a failed export never leaves a truncated report.
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use grin_wallet_api::Owner;
+use grin_wallet_impls::{Error, ErrorKind};
+use grin_wallet_libwallet::{TxLogEntry, TxLogEntryType};
+use grin_wallet_util::grin_util::to_hex;
+
+use crate::error::LibError;
+use crate::history::{tx_amount, SortOrder, TxQuery};
+use crate::metadata::{all_tx_metadata, TxMetadata};
+use crate::session::WalletInstance;
+use crate::MobileWalletCfg;
+
+/// Report format
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn from_str(format: &str) -> Result<Self, Error> {
+        match format {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            _ => Err(Error::from(ErrorKind::ArgumentError(format!(
+                "unknown export format: {}",
+                format
+            )))),
+        }
+    }
+}
+
+/// The dates of the transactions to export, both optional
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct DateRange {
+    /// Unix timestamps of the creation, in seconds, both included
+    pub from_date: Option<i64>,
+    pub to_date: Option<i64>,
+}
+
+impl DateRange {
+    /// Parse a date range, a null argument meaning all the transactions.
+    pub fn from_str(json_range: Option<String>) -> Result<Self, Error> {
+        match json_range {
+            Some(json_range) => serde_json::from_str::<DateRange>(&json_range)
+                .map_err(|e| Error::from(ErrorKind::ArgumentError(e.to_string()))),
+            None => Ok(DateRange::default()),
+        }
+    }
+}
+
+const CSV_HEADER: &str = "date,direction,amount,amount_nanogrin,fee,fee_nanogrin,status,\
+                          kernel_excess,slate_id,note,tags,counterparty";
+
+/// A transaction of a report
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct TxRecord<'a> {
+    /// RFC 3339 date of the creation
+    date: String,
+    direction: &'static str,
+    /// The amount in GRIN, without the fee
+    amount: String,
+    amount_nanogrin: u64,
+    fee: String,
+    fee_nanogrin: u64,
+    status: &'static str,
+    kernel_excess: Option<String>,
+    slate_id: Option<String>,
+    note: Option<&'a str>,
+    tags: Vec<&'a str>,
+    counterparty: Option<&'a str>,
+}
+
+impl<'a> TxRecord<'a> {
+    fn new(
+        tx: &TxLogEntry,
+        kernel_excess: Option<String>,
+        metadata: Option<&'a TxMetadata>,
+    ) -> Self {
+        let fee = tx.fee.unwrap_or(0);
+        let (direction, amount) = match tx.tx_type {
+            TxLogEntryType::TxSent | TxLogEntryType::TxSentCancelled => {
+                ("sent", tx_amount(tx).saturating_sub(fee))
+            }
+            TxLogEntryType::TxReceived | TxLogEntryType::TxReceivedCancelled => {
+                ("received", tx_amount(tx))
+            }
+            TxLogEntryType::ConfirmedCoinbase => ("coinbase", tx_amount(tx)),
+        };
+        let status = match tx.tx_type {
+            TxLogEntryType::TxSentCancelled | TxLogEntryType::TxReceivedCancelled => "cancelled",
+            _ if tx.confirmed => "confirmed",
+            _ => "unconfirmed",
+        };
+        TxRecord {
+            date: tx.creation_ts.to_rfc3339(),
+            direction,
+            amount: format_grin(amount),
+            amount_nanogrin: amount,
+            fee: format_grin(fee),
+            fee_nanogrin: fee,
+            status,
+            kernel_excess,
+            slate_id: tx.tx_slate_id.map(|id| id.to_string()),
+            note: metadata.and_then(|m| m.note.as_ref().map(|n| n.as_str())),
+            tags: metadata.map_or(vec![], |m| m.tags.iter().map(|t| t.as_str()).collect()),
+            counterparty: metadata.and_then(|m| m.counterparty.as_ref().map(|c| c.as_str())),
+        }
+    }
+
+    fn write_csv<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        let fields = [
+            self.date.clone(),
+            self.direction.to_owned(),
+            self.amount.clone(),
+            self.amount_nanogrin.to_string(),
+            self.fee.clone(),
+            self.fee_nanogrin.to_string(),
+            self.status.to_owned(),
+            self.kernel_excess.clone().unwrap_or_default(),
+            self.slate_id.clone().unwrap_or_default(),
+            self.note.unwrap_or("").to_owned(),
+            self.tags.join(";"),
+            self.counterparty.unwrap_or("").to_owned(),
+        ];
+        let fields: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        writeln!(w, "{}", fields.join(","))
+    }
+}
+
+/// An amount in GRIN, with the 9 decimals of the nanogrin
+fn format_grin(amount: u64) -> String {
+    format!("{}.{:09}", amount / 1_000_000_000, amount % 1_000_000_000)
+}
+
+/// Quote a CSV field if it needs to be
+fn csv_field(field: &str) -> String {
+    if field.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> Error {
+    LibError::Io.into_error(format!("fail to write {}: {}", path.display(), e))
+}
+
+/// Export the transactions created in a date range to a report file, the oldest
+/// firstly, and return the number of the exported transactions.
+pub fn export_txs(
+    config: &MobileWalletCfg,
+    wallet: WalletInstance,
+    format: ExportFormat,
+    path: &Path,
+    date_range: &DateRange,
+) -> Result<usize, Error> {
+    let query = TxQuery {
+        from_date: date_range.from_date,
+        to_date: date_range.to_date,
+        order: SortOrder::Asc,
+        ..TxQuery::default()
+    };
+    query.validate()?;
+
+    let api = Owner::new(wallet);
+    let (_, txs) = api.retrieve_txs(true, None, None)?;
+    let (count, txs) = query.apply(txs);
+    let metadata = all_tx_metadata(config)?;
+
+    let tmp_path = path.with_extension("tmp");
+    let write = || -> Result<(), Error> {
+        let file = File::create(&tmp_path).map_err(|e| io_error(&tmp_path, e))?;
+        let mut w = BufWriter::new(file);
+        match format {
+            ExportFormat::Csv => writeln!(w, "{}", CSV_HEADER),
+            ExportFormat::Json => write!(w, "["),
+        }
+        .map_err(|e| io_error(&tmp_path, e))?;
+
+        for (i, tx) in txs.iter().enumerate() {
+            // A stored transaction which can't be read only leaves its kernel excess out
+            let kernel_excess = api
+                .get_stored_tx(tx)
+                .ok()
+                .and_then(|t| t)
+                .and_then(|t| t.kernels().first().map(|k| to_hex(k.excess.0.to_vec())));
+            let tx_metadata = tx.tx_slate_id.and_then(|id| metadata.get(&id.to_string()));
+            let record = TxRecord::new(tx, kernel_excess, tx_metadata);
+            match format {
+                ExportFormat::Csv => record.write_csv(&mut w),
+                ExportFormat::Json => {
+                    if i > 0 {
+                        write!(w, ",").map_err(|e| io_error(&tmp_path, e))?;
+                    }
+                    serde_json::to_writer(&mut w, &record).map_err(std::io::Error::from)
+                }
+            }
+            .map_err(|e| io_error(&tmp_path, e))?;
+        }
+
+        if format == ExportFormat::Json {
+            writeln!(w, "]").map_err(|e| io_error(&tmp_path, e))?;
+        }
+        w.flush().map_err(|e| io_error(&tmp_path, e))
+    };
+
+    let res = write().and_then(|_| fs::rename(&tmp_path, path).map_err(|e| io_error(path, e)));
+    if res.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    res.map(|_| count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_grin_with_nine_decimals() {
+        assert_eq!(format_grin(0), "0.000000000");
+        assert_eq!(format_grin(1), "0.000000001");
+        assert_eq!(format_grin(1_000_000_000), "1.000000000");
+        assert_eq!(format_grin(12_345_678_901), "12.345678901");
+        assert_eq!(format_grin(u64::max_value()), "18446744073.709551615");
+    }
+
+    #[test]
+    fn quotes_csv_fields_only_when_needed() {
+        assert_eq!(csv_field(""), "");
+        assert_eq!(csv_field("coffee"), "coffee");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field("cr\r"), "\"cr\r\"");
+    }
+
+    #[test]
+    fn parses_formats_and_ranges() {
+        assert_eq!(ExportFormat::from_str("csv").unwrap(), ExportFormat::Csv);
+        assert_eq!(ExportFormat::from_str("json").unwrap(), ExportFormat::Json);
+        assert!(ExportFormat::from_str("CSV").is_err());
+
+        let range = DateRange::from_str(Some(r#"{"from_date": 1}"#.to_owned())).unwrap();
+        assert_eq!((range.from_date, range.to_date), (Some(1), None));
+        assert!(DateRange::from_str(Some(r#"{"from": 1}"#.to_owned())).is_err());
+    }
+}
@@ -0,0 +1,286 @@
+// Copyright 2019 Gary Yu.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Filtered and paginated queries of the transaction history.
+
+use serde::{Deserialize, Serialize};
+
+use grin_wallet_impls::{Error, ErrorKind};
+use grin_wallet_libwallet::{TxLogEntry, TxLogEntryType};
+
+/// Transaction type filter
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TxType {
+    Sent,
+    Received,
+    Cancelled,
+    Coinbase,
+}
+
+impl TxType {
+    fn matches(self, tx_type: &TxLogEntryType) -> bool {
+        match tx_type {
+            TxLogEntryType::TxSent => self == TxType::Sent,
+            TxLogEntryType::TxReceived => self == TxType::Received,
+            TxLogEntryType::TxSentCancelled | TxLogEntryType::TxReceivedCancelled => {
+                self == TxType::Cancelled
+            }
+            TxLogEntryType::ConfirmedCoinbase => self == TxType::Coinbase,
+        }
+    }
+}
+
+/// Sort order, by creation time
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl Default for SortOrder {
+    fn default() -> Self {
+        SortOrder::Desc
+    }
+}
+
+/// A transaction history query, all the fields are optional
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TxQuery {
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+    pub tx_type: Option<TxType>,
+    pub confirmed: Option<bool>,
+    /// Unix timestamps of the creation, in seconds, both included
+    pub from_date: Option<i64>,
+    pub to_date: Option<i64>,
+    /// Bounds of the amount, i.e. the absolute value of the credited amount
+    /// minus the debited amount, both included
+    pub min_amount: Option<u64>,
+    pub max_amount: Option<u64>,
+    #[serde(default)]
+    pub order: SortOrder,
+}
+
+/// A page of the transaction history
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TxPage {
+    /// The transactions were refreshed from the node
+    pub validated: bool,
+    /// Number of the transactions matching the filters, of all the pages
+    pub total: usize,
+    /// The transactions, with their metadata
+    pub txs: Vec<serde_json::Value>,
+}
+
+/// The amount of a transaction, see `TxQuery::min_amount`
+pub fn tx_amount(tx: &TxLogEntry) -> u64 {
+    if tx.amount_credited >= tx.amount_debited {
+        tx.amount_credited - tx.amount_debited
+    } else {
+        tx.amount_debited - tx.amount_credited
+    }
+}
+
+impl TxQuery {
+    /// Parse a query, a null argument meaning all the transactions.
+    pub fn from_str(json_query: Option<String>) -> Result<Self, Error> {
+        let query = match json_query {
+            Some(json_query) => serde_json::from_str::<TxQuery>(&json_query)
+                .map_err(|e| Error::from(ErrorKind::ArgumentError(e.to_string())))?,
+            None => TxQuery::default(),
+        };
+        query.validate()?;
+        Ok(query)
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        if let (Some(from), Some(to)) = (self.from_date, self.to_date) {
+            if from > to {
+                return Err(Error::from(ErrorKind::ArgumentError(format!(
+                    "from_date {} is after to_date {}",
+                    from, to
+                ))));
+            }
+        }
+        if let (Some(min), Some(max)) = (self.min_amount, self.max_amount) {
+            if min > max {
+                return Err(Error::from(ErrorKind::ArgumentError(format!(
+                    "min_amount {} is greater than max_amount {}",
+                    min, max
+                ))));
+            }
+        }
+        Ok(())
+    }
+
+    fn matches(&self, tx: &TxLogEntry) -> bool {
+        let created = tx.creation_ts.timestamp();
+        let amount = tx_amount(tx);
+        self.tx_type.map_or(true, |t| t.matches(&tx.tx_type))
+            && self.confirmed.map_or(true, |c| tx.confirmed == c)
+            && self.from_date.map_or(true, |from| created >= from)
+            && self.to_date.map_or(true, |to| created <= to)
+            && self.min_amount.map_or(true, |min| amount >= min)
+            && self.max_amount.map_or(true, |max| amount <= max)
+    }
+
+    /// Filter and sort the transactions, and return the number of the matching
+    /// transactions with the requested page of them.
+    pub fn apply(&self, txs: Vec<TxLogEntry>) -> (usize, Vec<TxLogEntry>) {
+        let mut txs: Vec<TxLogEntry> = txs.into_iter().filter(|tx| self.matches(tx)).collect();
+        txs.sort_by_key(|tx| (tx.creation_ts, tx.id));
+        if self.order == SortOrder::Desc {
+            txs.reverse();
+        }
+        let total = txs.len();
+        let txs = txs
+            .into_iter()
+            .skip(self.offset.unwrap_or(0))
+            .take(self.limit.unwrap_or(total))
+            .collect();
+        (total, txs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grin_wallet_util::grin_keychain::{ExtKeychain, Keychain};
+
+    const SEPT_1_2019: i64 = 1_567_296_000;
+
+    /// A transaction created at midnight of a day of September 2019
+    fn tx(id: u32, tx_type: TxLogEntryType, day: u32, credited: u64, debited: u64) -> TxLogEntry {
+        let parent_key_id = ExtKeychain::derive_key_id(2, 0, 0, 0, 0);
+        let mut value = serde_json::to_value(TxLogEntry::new(parent_key_id, tx_type, id)).unwrap();
+        value["creation_ts"] = format!("2019-09-{:02}T00:00:00Z", day).into();
+        let mut tx: TxLogEntry = serde_json::from_value(value).unwrap();
+        tx.amount_credited = credited;
+        tx.amount_debited = debited;
+        tx.confirmed = true;
+        tx
+    }
+
+    fn day(day: u32) -> i64 {
+        SEPT_1_2019 + (day as i64 - 1) * 86400
+    }
+
+    fn txs() -> Vec<TxLogEntry> {
+        let mut unconfirmed = tx(3, TxLogEntryType::TxReceived, 3, 300, 0);
+        unconfirmed.confirmed = false;
+        vec![
+            tx(0, TxLogEntryType::TxSent, 1, 50, 150),
+            tx(1, TxLogEntryType::TxReceived, 2, 200, 0),
+            tx(2, TxLogEntryType::TxSentCancelled, 2, 0, 0),
+            unconfirmed,
+            tx(4, TxLogEntryType::ConfirmedCoinbase, 5, 1000, 0),
+        ]
+    }
+
+    fn ids(txs: &[TxLogEntry]) -> Vec<u32> {
+        txs.iter().map(|tx| tx.id).collect()
+    }
+
+    #[test]
+    fn sorts_by_creation_then_id() {
+        let (total, page) = TxQuery::default().apply(txs());
+        assert_eq!(total, 5);
+        assert_eq!(ids(&page), vec![4, 3, 2, 1, 0]);
+
+        let query = TxQuery {
+            order: SortOrder::Asc,
+            ..Default::default()
+        };
+        assert_eq!(ids(&query.apply(txs()).1), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn pages_after_filtering() {
+        let query = TxQuery {
+            offset: Some(1),
+            limit: Some(2),
+            ..Default::default()
+        };
+        let (total, page) = query.apply(txs());
+        assert_eq!(total, 5);
+        assert_eq!(ids(&page), vec![3, 2]);
+
+        let query = TxQuery {
+            offset: Some(1),
+            limit: Some(5),
+            tx_type: Some(TxType::Received),
+            ..Default::default()
+        };
+        let (total, page) = query.apply(txs());
+        assert_eq!(total, 2);
+        assert_eq!(ids(&page), vec![1]);
+
+        let query = TxQuery {
+            offset: Some(10),
+            ..Default::default()
+        };
+        let (total, page) = query.apply(txs());
+        assert_eq!(total, 5);
+        assert!(page.is_empty());
+    }
+
+    #[test]
+    fn filters_by_type_and_confirmation() {
+        let filtered = |tx_type, confirmed| {
+            let query = TxQuery {
+                tx_type,
+                confirmed,
+                ..Default::default()
+            };
+            ids(&query.apply(txs()).1)
+        };
+        assert_eq!(filtered(Some(TxType::Sent), None), vec![0]);
+        assert_eq!(filtered(Some(TxType::Cancelled), None), vec![2]);
+        assert_eq!(filtered(Some(TxType::Coinbase), None), vec![4]);
+        assert_eq!(filtered(None, Some(false)), vec![3]);
+        assert_eq!(filtered(Some(TxType::Received), Some(true)), vec![1]);
+    }
+
+    #[test]
+    fn filters_by_date_and_amount_bounds_included() {
+        let query = TxQuery {
+            from_date: Some(day(2)),
+            to_date: Some(day(3)),
+            ..Default::default()
+        };
+        assert_eq!(ids(&query.apply(txs()).1), vec![3, 2, 1]);
+
+        // The amount of a sent transaction is its debited amount minus its change
+        let query = TxQuery {
+            min_amount: Some(100),
+            max_amount: Some(300),
+            ..Default::default()
+        };
+        assert_eq!(ids(&query.apply(txs()).1), vec![3, 1, 0]);
+    }
+
+    #[test]
+    fn rejects_reversed_bounds() {
+        assert!(TxQuery::from_str(Some(r#"{"from_date": 2, "to_date": 1}"#.to_owned())).is_err());
+        assert!(
+            TxQuery::from_str(Some(r#"{"min_amount": 2, "max_amount": 1}"#.to_owned())).is_err()
+        );
+        assert!(TxQuery::from_str(Some(r#"{"page": 1}"#.to_owned())).is_err());
+        assert!(TxQuery::from_str(None).is_ok());
+    }
+}
@@ -0,0 +1,144 @@
+// Copyright 2019 Gary Yu.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Invoices exchanged over Grin Relay.
+//!
+//! The invoices we issued are kept until the payer's response is finalized, and the
+//! invoices we received are kept until the user pays or rejects them.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use grin_wallet_api::{Foreign, Owner};
+use grin_wallet_impls::Error;
+use grin_wallet_libwallet::Slate;
+
+use crate::session::WalletInstance;
+use crate::store;
+use crate::MobileWalletCfg;
+
+const ISSUED_INVOICES_FILE: &str = "issued_invoices.json";
+const RECEIVED_INVOICES_FILE: &str = "received_invoices.json";
+
+/// An invoice we sent, waiting for the payer's response
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IssuedInvoice {
+    pub slate_id: String,
+    /// The relay address of the payer
+    pub addr: String,
+    pub amount: u64,
+    /// Unix timestamp of the issue, in seconds
+    pub created_at: u64,
+}
+
+/// An invoice sent to us, waiting for the user's approval
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceivedInvoice {
+    /// The relay address of the payee
+    pub addr: String,
+    pub slate: Slate,
+    /// Unix timestamp of the reception, in seconds
+    pub received_at: u64,
+}
+
+/// Whether a slate is an invoice, i.e. it's initiated by the payee, who is
+/// the participant 1, so the payer hasn't signed yet.
+pub fn is_invoice(slate: &Slate) -> bool {
+    !slate.participant_data.is_empty() && slate.participant_data.iter().all(|p| p.id != 0)
+}
+
+/// Finalize the payer's response of an invoice we issued, without posting it.
+pub fn finalize_invoice_response(wallet: WalletInstance, slate: &Slate) -> Result<Slate, Error> {
+    let foreign = Foreign::new(wallet, None);
+    foreign.verify_slate_messages(slate)?;
+    foreign.finalize_invoice_tx(slate)
+}
+
+/// Finalize the payer's response of an invoice we issued, and post the transaction.
+pub fn finalize_invoice(wallet: WalletInstance, slate: &Slate) -> Result<Slate, Error> {
+    let api = Owner::new(wallet.clone());
+    let finalized_slate = finalize_invoice_response(wallet, slate);
+    if finalized_slate.is_err() {
+        api.cancel_tx(None, Some(slate.id))?;
+    }
+    let finalized_slate = finalized_slate?;
+
+    let res = api.post_tx(&finalized_slate.tx, false);
+    if res.is_err() {
+        api.cancel_tx(None, Some(slate.id))?;
+        res?;
+    }
+    Ok(finalized_slate)
+}
+
+pub fn add_issued_invoice(config: &MobileWalletCfg, invoice: IssuedInvoice) -> Result<(), Error> {
+    let path = store::wallet_data_file(config, ISSUED_INVOICES_FILE);
+    store::update(&path, |invoices: &mut HashMap<String, IssuedInvoice>| {
+        invoices.insert(invoice.slate_id.clone(), invoice);
+    })
+}
+
+/// An issued invoice, if it exists
+pub fn issued_invoice(
+    config: &MobileWalletCfg,
+    slate_id: &str,
+) -> Result<Option<IssuedInvoice>, Error> {
+    let path = store::wallet_data_file(config, ISSUED_INVOICES_FILE);
+    let mut invoices: HashMap<String, IssuedInvoice> = store::load(&path)?;
+    Ok(invoices.remove(slate_id))
+}
+
+/// Remove an issued invoice, and return it if it exists.
+pub fn take_issued_invoice(
+    config: &MobileWalletCfg,
+    slate_id: &str,
+) -> Result<Option<IssuedInvoice>, Error> {
+    let path = store::wallet_data_file(config, ISSUED_INVOICES_FILE);
+    store::update(&path, |invoices: &mut HashMap<String, IssuedInvoice>| {
+        invoices.remove(slate_id)
+    })
+}
+
+pub fn add_received_invoice(
+    config: &MobileWalletCfg,
+    invoice: ReceivedInvoice,
+) -> Result<(), Error> {
+    let path = store::wallet_data_file(config, RECEIVED_INVOICES_FILE);
+    store::update(&path, |invoices: &mut HashMap<String, ReceivedInvoice>| {
+        invoices.insert(invoice.slate.id.to_string(), invoice);
+    })
+}
+
+/// Remove a received invoice, and return it if it exists.
+pub fn take_received_invoice(
+    config: &MobileWalletCfg,
+    slate_id: &str,
+) -> Result<Option<ReceivedInvoice>, Error> {
+    let path = store::wallet_data_file(config, RECEIVED_INVOICES_FILE);
+    store::update(&path, |invoices: &mut HashMap<String, ReceivedInvoice>| {
+        invoices.remove(slate_id)
+    })
+}
+
+/// The received invoices waiting for the user's approval, the oldest firstly.
+pub fn received_invoices(config: &MobileWalletCfg) -> Result<Vec<ReceivedInvoice>, Error> {
+    let path = store::wallet_data_file(config, RECEIVED_INVOICES_FILE);
+    let invoices: HashMap<String, ReceivedInvoice> = store::load(&path)?;
+    let mut invoices: Vec<ReceivedInvoice> = invoices.into_iter().map(|(_, i)| i).collect();
+    invoices.sort_by_key(|i| i.received_at);
+    Ok(invoices)
+}
@@ -14,13 +14,33 @@
 
 //! Libs Wallet External API Definition
 
+#[macro_use]
+extern crate lazy_static;
+
+mod attempts;
+mod coins;
+mod error;
+mod events;
+mod export;
+mod history;
+mod invoice;
+mod listener;
+mod metadata;
+mod mnemonic;
+mod pending;
+mod passphrase;
+mod proof;
+mod session;
+mod store;
+
+use std::any::Any;
 use std::ffi::{CStr, CString};
 use std::os::raw::c_char;
-use std::sync::Arc;
-use std::thread;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
 use std::time::Duration;
-use std::sync::mpsc::{channel, TryRecvError};
 
+use serde::de::IgnoredAny;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use uuid::Uuid;
@@ -28,21 +48,110 @@ use uuid::Uuid;
 use grin_wallet_api::{Foreign, Owner};
 use grin_wallet_config::{GrinRelayConfig, WalletConfig};
 use grin_wallet_impls::{
-    instantiate_wallet, Error, ErrorKind, FileWalletCommAdapter, HTTPNodeClient,
-    HTTPWalletCommAdapter, LMDBBackend, GrinrelayWalletCommAdapter, WalletSeed,
+    Error, ErrorKind, FileWalletCommAdapter, HTTPNodeClient,
+    HTTPWalletCommAdapter, LMDBBackend, WalletSeed,
 };
-use grin_wallet_libwallet::api_impl::types::InitTxArgs;
-use grin_wallet_libwallet::{NodeClient, WalletInst, SlateVersion, VersionedSlate};
+use grin_wallet_libwallet::api_impl::types::{InitTxArgs, IssueInvoiceTxArgs};
+use grin_wallet_libwallet::{AcctPathMapping, Slate, TxLogEntryType};
 use grin_wallet_util::grin_core::global::ChainTypes;
+use grin_wallet_util::grin_core::libtx::tx_fee;
 use grin_wallet_util::grin_keychain::ExtKeychain;
-use grin_wallet_util::grin_util::{file::get_first_line, Mutex, ZeroingString};
-use grin_wallet_controller::grinrelay_listener;
+use grin_wallet_util::grin_util::{file::get_first_line, ZeroingString};
+
+use crate::attempts::{
+    check_wipe_after_failures, password_attempts, reset_password_attempts, verify_password,
+};
+use crate::coins::{frozen_outputs, set_frozen, with_coin_control};
+use crate::error::{ErrorCategory, ErrorEnvelope, LibError, CODE_GENERIC, CODE_PANIC};
+use crate::events::{poll_events, unix_timestamp};
+use crate::export::{export_txs, DateRange, ExportFormat};
+use crate::history::{TxPage, TxQuery};
+use crate::invoice::{
+    add_issued_invoice, add_received_invoice, finalize_invoice, is_invoice, received_invoices,
+    take_issued_invoice, take_received_invoice, IssuedInvoice,
+};
+use crate::listener::{listener_status, publish_slate, start_listener, stop_listener, wait_ready};
+use crate::metadata::{
+    search_tx_metadata, set_counterparty, set_tx_metadata, tx_metadata, with_tx_metadata,
+    TxMetadata,
+};
+use crate::mnemonic::{normalize_phrase, suggest_words, validate_phrase};
+use crate::pending::{add_pending_send, take_pending_send, PendingSend};
+use crate::proof::{payment_proof, proof_address, verify_payment_proof, PaymentProof};
+use crate::session::{
+    close_session, get_session, open_session, reopen_session, with_session, with_session_read,
+    Access, WalletSession,
+};
 
 /// Default minimum confirmation
 pub const MINIMUM_CONFIRMATIONS: u64 = 10;
 
-fn cstr_to_str(s: *const c_char) -> String {
-    unsafe { CStr::from_ptr(s).to_string_lossy().into_owned() }
+fn default_minimum_confirmations() -> u64 {
+    MINIMUM_CONFIRMATIONS
+}
+
+/// Default time to wait for the Grin Relay listener to be ready, in seconds
+pub const DEFAULT_GRINRELAY_READY_TIMEOUT_SECS: u64 = 10;
+
+/// Convert a required string argument, rejecting a null pointer and invalid UTF-8
+fn cstr_to_str(s: *const c_char, name: &str) -> Result<String, Error> {
+    cstr_to_opt_str(s)?.ok_or_else(|| {
+        Error::from(ErrorKind::ArgumentError(format!("{} is null", name)))
+    })
+}
+
+/// Convert an optional string argument, a null pointer meaning no value
+fn cstr_to_opt_str(s: *const c_char) -> Result<Option<String>, Error> {
+    if s.is_null() {
+        return Ok(None);
+    }
+    let s = unsafe { CStr::from_ptr(s) };
+    match s.to_str() {
+        Ok(s) => Ok(Some(s.to_owned())),
+        Err(e) => Err(Error::from(ErrorKind::ArgumentError(format!(
+            "invalid UTF-8 string: {}",
+            e
+        )))),
+    }
+}
+
+/// Convert an optional secret argument, a null pointer or an empty string meaning no value
+fn cstr_to_opt_secret(s: *const c_char, name: &str) -> Result<Option<ZeroingString>, Error> {
+    if s.is_null() {
+        return Ok(None);
+    }
+    let s = cstr_to_secret(s, name)?;
+    Ok(if s.is_empty() { None } else { Some(s) })
+}
+
+/// Convert a secret argument, like a password or a mnemonic, which is never altered
+/// and is zeroed on drop.
+fn cstr_to_secret(s: *const c_char, name: &str) -> Result<ZeroingString, Error> {
+    if s.is_null() {
+        return Err(Error::from(ErrorKind::ArgumentError(format!(
+            "{} is null",
+            name
+        ))));
+    }
+    let s = unsafe { CStr::from_ptr(s) };
+    match s.to_str() {
+        Ok(s) => Ok(ZeroingString::from(s)),
+        Err(_) => Err(Error::from(ErrorKind::ArgumentError(format!(
+            "{} is not a valid UTF-8 string",
+            name
+        )))),
+    }
+}
+
+/// Open the wallet of a config argument, with the password argument
+fn session_from_json(
+    json_cfg: *const c_char,
+    password: *const c_char,
+) -> Result<WalletSession, Error> {
+    WalletSession::from_json(
+        &cstr_to_str(json_cfg, "json_cfg")?,
+        &cstr_to_secret(password, "password")?,
+    )
 }
 
 #[no_mangle]
@@ -56,36 +165,78 @@ pub extern "C" fn cstr_free(s: *mut c_char) {
     };
 }
 
-unsafe fn result_to_cstr(res: Result<String, Error>, error: *mut u8) -> *const c_char {
-    match res {
+/// Run the body of an exported function, converting a panic into an error,
+/// since a panic unwinding across the C ABI would abort the app.
+fn catch_panic<T, F>(f: F) -> Result<T, ErrorEnvelope>
+where
+    F: FnOnce() -> Result<T, Error>,
+{
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(res) => res.map_err(|e| ErrorEnvelope::from(&e)),
+        Err(payload) => {
+            let message = panic_message(payload.as_ref());
+            Err(ErrorEnvelope::new(CODE_PANIC, ErrorCategory::Internal, &message))
+        }
+    }
+}
+
+/// The message of a caught panic
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic".to_owned()
+    }
+}
+
+fn string_to_cstr(s: String) -> Result<*const c_char, ErrorEnvelope> {
+    CString::new(s)
+        .map(|s| s.into_raw() as *const c_char)
+        .map_err(|e| ErrorEnvelope::new(CODE_GENERIC, ErrorCategory::Internal, &e.to_string()))
+}
+
+fn error_to_cstr(e: ErrorEnvelope) -> *const c_char {
+    // A serialized error never has an interior nul byte
+    CString::new(e.to_json()).unwrap_or_default().into_raw()
+}
+
+unsafe fn set_error(error: *mut u8, value: u8) {
+    if !error.is_null() {
+        *error = value;
+    }
+}
+
+unsafe fn result_to_cstr(res: Result<String, ErrorEnvelope>, error: *mut u8) -> *const c_char {
+    match res.and_then(string_to_cstr) {
         Ok(res) => {
-            *error = 0;
-            CString::new(res).unwrap().into_raw()
+            set_error(error, 0);
+            res
         }
         Err(e) => {
-            *error = 1;
-            CString::new(serde_json::to_string(&format!("{}", e)).unwrap())
-                .unwrap()
-                .into_raw()
+            set_error(error, 1);
+            error_to_cstr(e)
         }
     }
 }
 
-unsafe fn result2_to_cstr(res: Result<(bool, String), Error>, error: *mut u8) -> *const c_char {
-    match res {
+unsafe fn result2_to_cstr(
+    res: Result<(bool, String), ErrorEnvelope>,
+    error: *mut u8,
+) -> *const c_char {
+    match res.and_then(|(validated, res)| Ok((validated, string_to_cstr(res)?))) {
         Ok((validated, res)) => {
             if validated {
-                *error = 0;
+                set_error(error, 0);
             } else {
-                *error = 2;
+                set_error(error, 2);
             }
-            CString::new(res).unwrap().into_raw()
+            res
         }
         Err(e) => {
-            *error = 1;
-            CString::new(serde_json::to_string(&format!("{}", e)).unwrap())
-                .unwrap()
-                .into_raw()
+            set_error(error, 1);
+            error_to_cstr(e)
         }
     }
 }
@@ -96,15 +247,172 @@ struct MobileWalletCfg {
     chain_type: String,
     data_dir: String,
     node_api_addr: String,
-    password: String,
+    /// Always rejected: the password is never part of the config, it's passed separately
+    #[serde(default, skip_serializing)]
+    password: Option<IgnoredAny>,
+    /// Confirmations of an output to be spendable, also used for the balance
+    #[serde(default = "default_minimum_confirmations")]
     minimum_confirmations: u64,
     grinrelay_config: Option<GrinRelayConfig>,
+    grinrelay_ready_timeout_secs: Option<u64>,
+    /// Idle time after which a session is locked, in seconds, none meaning never
+    lock_timeout_secs: Option<u64>,
+    /// Read-only calls still work on a locked session
+    #[serde(default)]
+    read_when_locked: bool,
+    /// Wrong passwords in a row after which the wallet data is wiped, none meaning never.
+    /// It's saved by the wallet init, recovery and open, and ignored by the other calls.
+    wipe_after_failures: Option<u32>,
+    /// Directory of the files of the wallet opened with a passphrase, set when it's opened
+    #[serde(skip)]
+    wallet_dir: Option<String>,
 }
 
 impl MobileWalletCfg {
     pub fn from_str(json_cfg: &str) -> Result<Self, Error> {
-        serde_json::from_str::<MobileWalletCfg>(json_cfg)
-            .map_err(|e| Error::from(ErrorKind::GenericError(e.to_string())))
+        let config = serde_json::from_str::<MobileWalletCfg>(json_cfg)
+            .map_err(|e| Error::from(ErrorKind::ArgumentError(e.to_string())))?;
+        if config.password.is_some() {
+            return Err(Error::from(ErrorKind::ArgumentError(
+                "json_cfg must not contain the password, it's passed separately".to_owned(),
+            )));
+        }
+        check_minimum_confirmations(config.minimum_confirmations)?;
+        check_wipe_after_failures(config.wipe_after_failures)?;
+        Ok(config)
+    }
+}
+
+/// Maximum number of inputs of a send
+pub const MAX_SEND_OUTPUTS: u32 = 500;
+/// Maximum number of change outputs of a send
+pub const MAX_CHANGE_OUTPUTS: u32 = 32;
+/// Maximum minimum confirmation, i.e. about one week
+pub const MAX_MINIMUM_CONFIRMATIONS: u64 = 10080;
+
+fn check_minimum_confirmations(minimum_confirmations: u64) -> Result<(), Error> {
+    if minimum_confirmations < 1 || minimum_confirmations > MAX_MINIMUM_CONFIRMATIONS {
+        return Err(Error::from(ErrorKind::ArgumentError(format!(
+            "minimum_confirmations {} is not in [1, {}]",
+            minimum_confirmations, MAX_MINIMUM_CONFIRMATIONS
+        ))));
+    }
+    Ok(())
+}
+
+/// Coin selection parameters of a send, all optional
+#[derive(Deserialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+struct SendOptions {
+    minimum_confirmations: Option<u64>,
+    max_outputs: Option<u32>,
+    num_change_outputs: Option<u32>,
+    /// The account to send from, instead of the active account
+    src_acct_name: Option<String>,
+    /// The commitments of the outputs to spend, instead of the coin selection
+    input_commits: Option<Vec<String>>,
+}
+
+impl SendOptions {
+    /// Parse the send options, a null argument meaning the default options.
+    pub fn from_str(json_options: Option<String>) -> Result<Self, Error> {
+        let options = match json_options {
+            Some(json_options) => serde_json::from_str::<SendOptions>(&json_options)
+                .map_err(|e| Error::from(ErrorKind::ArgumentError(e.to_string())))?,
+            None => SendOptions::default(),
+        };
+        options.validate()?;
+        Ok(options)
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        if let Some(n) = self.minimum_confirmations {
+            check_minimum_confirmations(n)?;
+        }
+        if let Some(n) = self.max_outputs {
+            if n < 1 || n > MAX_SEND_OUTPUTS {
+                return Err(Error::from(ErrorKind::ArgumentError(format!(
+                    "max_outputs {} is not in [1, {}]",
+                    n, MAX_SEND_OUTPUTS
+                ))));
+            }
+        }
+        if let Some(n) = self.num_change_outputs {
+            if n < 1 || n > MAX_CHANGE_OUTPUTS {
+                return Err(Error::from(ErrorKind::ArgumentError(format!(
+                    "num_change_outputs {} is not in [1, {}]",
+                    n, MAX_CHANGE_OUTPUTS
+                ))));
+            }
+        }
+        if let Some(ref input_commits) = self.input_commits {
+            if input_commits.is_empty() || input_commits.len() > MAX_SEND_OUTPUTS as usize {
+                return Err(Error::from(ErrorKind::ArgumentError(format!(
+                    "input_commits size {} is not in [1, {}]",
+                    input_commits.len(),
+                    MAX_SEND_OUTPUTS
+                ))));
+            }
+        }
+        if let Some(ref account) = self.src_acct_name {
+            if account.trim().is_empty() {
+                return Err(Error::from(ErrorKind::ArgumentError(
+                    "src_acct_name is empty".to_owned(),
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// The minimum confirmation of the options, or else of the wallet config
+    fn minimum_confirmations(&self, config: &MobileWalletCfg) -> u64 {
+        self.minimum_confirmations.unwrap_or(config.minimum_confirmations)
+    }
+
+    fn num_change_outputs(&self) -> usize {
+        self.num_change_outputs.unwrap_or(1) as usize
+    }
+
+    /// Build a new transaction by `f`, with the coin control of these options
+    fn with_coin_control<T, F>(&self, session: &WalletSession, f: F) -> Result<T, Error>
+    where
+        F: FnOnce() -> Result<T, Error>,
+    {
+        with_coin_control(
+            &session.config,
+            &session.wallet,
+            self.input_commits.as_ref().map(|c| c.as_slice()),
+            self.src_acct_name.as_ref().map(|a| a.as_str()),
+            self.minimum_confirmations(&session.config),
+            f,
+        )
+    }
+
+    /// The arguments of a new transaction with these options
+    fn init_tx_args(
+        &self,
+        config: &MobileWalletCfg,
+        amount: u64,
+        selection_strategy: &str,
+        target_slate_version: Option<u16>,
+        message: Option<String>,
+    ) -> InitTxArgs {
+        InitTxArgs {
+            src_acct_name: self.src_acct_name.clone(),
+            amount,
+            minimum_confirmations: self.minimum_confirmations(config),
+            max_outputs: self.max_outputs.unwrap_or(MAX_SEND_OUTPUTS),
+            num_change_outputs: self.num_change_outputs() as u32,
+            // All the selected outputs are spent
+            selection_strategy: match self.input_commits {
+                Some(_) => "all".to_owned(),
+                None => selection_strategy.to_string(),
+            },
+            message,
+            target_slate_version,
+            estimate_only: None,
+            send_args: None,
+        }
     }
 }
 
@@ -113,7 +421,7 @@ fn new_wallet_config(config: MobileWalletCfg) -> Result<WalletConfig, Error> {
         "mainnet" => ChainTypes::Mainnet,
         "floonet" => ChainTypes::Floonet,
         _ => {
-            return Err(Error::from(ErrorKind::GenericError(
+            return Err(Error::from(ErrorKind::ArgumentError(
                 "unsupported chain type".to_owned(),
             )));
         }
@@ -139,8 +447,7 @@ fn new_wallet_config(config: MobileWalletCfg) -> Result<WalletConfig, Error> {
 }
 
 fn check_password(json_cfg: &str, password: &str) -> Result<String, Error> {
-    let wallet_config = new_wallet_config(MobileWalletCfg::from_str(json_cfg)?)?;
-    WalletSeed::from_file(&wallet_config.data_file_dir, password).map_err(|e| Error::from(e))?;
+    verify_password(&MobileWalletCfg::from_str(json_cfg)?, password)?;
     Ok("OK".to_owned())
 }
 
@@ -150,7 +457,42 @@ pub extern "C" fn grin_check_password(
     password: *const c_char,
     error: *mut u8,
 ) -> *const c_char {
-    let res = check_password(&cstr_to_str(json_cfg), &cstr_to_str(password));
+    let res = catch_panic(|| {
+        check_password(
+            &cstr_to_str(json_cfg, "json_cfg")?,
+            &cstr_to_secret(password, "password")?,
+        )
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+fn get_password_attempts(config: &MobileWalletCfg) -> Result<String, Error> {
+    let attempts = password_attempts(config)?;
+    Ok(serde_json::to_string(&attempts).unwrap())
+}
+
+#[no_mangle]
+pub extern "C" fn grin_password_attempts(
+    json_cfg: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        MobileWalletCfg::from_str(&cstr_to_str(json_cfg, "json_cfg")?)
+            .and_then(|config| get_password_attempts(&config))
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_password_attempts(
+    handle: u64,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        // Not subject to the session lock, it's needed to unlock it
+        let session = get_session(handle)?;
+        get_password_attempts(&session.config)
+    });
     unsafe { result_to_cstr(res, error) }
 }
 
@@ -160,18 +502,38 @@ fn init_wallet_seed() -> Result<String, Error> {
 
 #[no_mangle]
 pub extern "C" fn grin_init_wallet_seed(error: *mut u8) -> *const c_char {
-    let res = init_wallet_seed();
+    let res = catch_panic(init_wallet_seed);
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_mnemonic_validate(phrase: *const c_char, error: *mut u8) -> *const c_char {
+    let res = catch_panic(|| {
+        let validation = validate_phrase(&cstr_to_secret(phrase, "phrase")?);
+        Ok(serde_json::to_string(&validation).unwrap())
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_mnemonic_suggest(prefix: *const c_char, error: *mut u8) -> *const c_char {
+    let res = catch_panic(|| {
+        let words = suggest_words(&cstr_to_str(prefix, "prefix")?);
+        Ok(serde_json::to_string(&words).unwrap())
+    });
     unsafe { result_to_cstr(res, error) }
 }
 
 fn wallet_init(json_cfg: &str, password: &str, is_12_phrases: bool) -> Result<String, Error> {
-    let wallet_config = new_wallet_config(MobileWalletCfg::from_str(json_cfg)?)?;
+    let config = MobileWalletCfg::from_str(json_cfg)?;
+    let wallet_config = new_wallet_config(config.clone())?;
     let node_api_secret = get_first_line(wallet_config.node_api_secret_path.clone());
     let seed_length = if is_12_phrases { 16 } else { 32 };
     let seed = WalletSeed::init_file(&wallet_config.data_file_dir, seed_length, None, password, false)?;
     let node_client = HTTPNodeClient::new(&wallet_config.check_node_api_http_addr, node_api_secret);
     let _: LMDBBackend<HTTPNodeClient, ExtKeychain> =
         LMDBBackend::new(wallet_config, password, node_client)?;
+    reset_password_attempts(&config)?;
     seed.to_mnemonic()
 }
 
@@ -182,18 +544,22 @@ pub extern "C" fn grin_wallet_init(
     is_12_phrases: bool,
     error: *mut u8,
 ) -> *const c_char {
-    let res = wallet_init(&cstr_to_str(json_cfg), &cstr_to_str(password), is_12_phrases);
+    let res = catch_panic(|| {
+        wallet_init(&cstr_to_str(json_cfg, "json_cfg")?, &cstr_to_secret(password, "password")?, is_12_phrases)
+    });
     unsafe { result_to_cstr(res, error) }
 }
 
-fn wallet_init_recover(json_cfg: &str, mnemonic: &str) -> Result<String, Error> {
+fn wallet_init_recover(json_cfg: &str, mnemonic: &str, password: &str) -> Result<String, Error> {
     let config = MobileWalletCfg::from_str(json_cfg)?;
     let wallet_config = new_wallet_config(config.clone())?;
-    WalletSeed::recover_from_phrase(&wallet_config.data_file_dir, mnemonic, config.password.as_str())?;
+    let phrase = normalize_phrase(mnemonic);
+    WalletSeed::recover_from_phrase(&wallet_config.data_file_dir, &phrase, password)?;
     let node_api_secret = get_first_line(wallet_config.node_api_secret_path.clone());
     let node_client = HTTPNodeClient::new(&wallet_config.check_node_api_http_addr, node_api_secret);
     let _: LMDBBackend<HTTPNodeClient, ExtKeychain> =
-        LMDBBackend::new(wallet_config, config.password.as_str(), node_client)?;
+        LMDBBackend::new(wallet_config, password, node_client)?;
+    reset_password_attempts(&config)?;
     Ok("OK".to_owned())
 }
 
@@ -201,22 +567,26 @@ fn wallet_init_recover(json_cfg: &str, mnemonic: &str) -> Result<String, Error>
 pub extern "C" fn grin_wallet_init_recover(
     json_cfg: *const c_char,
     mnemonic: *const c_char,
+    password: *const c_char,
     error: *mut u8,
 ) -> *const c_char {
-    let res = wallet_init_recover(
-        &cstr_to_str(json_cfg),
-        &cstr_to_str(mnemonic),
-    );
+    let res = catch_panic(|| {
+        wallet_init_recover(
+            &cstr_to_str(json_cfg, "json_cfg")?,
+            &cstr_to_secret(mnemonic, "mnemonic")?,
+            &cstr_to_secret(password, "password")?,
+        )
+    });
     unsafe { result_to_cstr(res, error) }
 }
 
 fn wallet_change_password(
-    json_cfg: &str,
+    session: &WalletSession,
     old_password: &str,
     new_password: &str,
 ) -> Result<String, Error> {
-    let wallet = get_wallet_instance(MobileWalletCfg::from_str(json_cfg)?)?;
-    let api = Owner::new(wallet);
+    verify_password(&session.config, old_password)?;
+    let api = Owner::new(session.wallet.clone());
 
     api.change_password(&Some(ZeroingString::from(old_password)), new_password)
         .map_err(|e| Error::from(e))?;
@@ -230,25 +600,60 @@ pub extern "C" fn grin_wallet_change_password(
     new_password: *const c_char,
     error: *mut u8,
 ) -> *const c_char {
-    let res = wallet_change_password(
-        &cstr_to_str(json_cfg),
-        &cstr_to_str(old_password),
-        &cstr_to_str(new_password),
-    );
+    let res = catch_panic(|| {
+        let old_password = cstr_to_secret(old_password, "old_password")?;
+        WalletSession::from_json(&cstr_to_str(json_cfg, "json_cfg")?, &old_password).and_then(
+            |session| {
+                wallet_change_password(
+                    &session,
+                    &old_password,
+                    &cstr_to_secret(new_password, "new_password")?,
+                )
+            },
+        )
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+fn session_change_password(
+    handle: u64,
+    old_password: &str,
+    new_password: &ZeroingString,
+) -> Result<String, Error> {
+    let session = get_session(handle)?;
+    session.check_access(Access::Full)?;
+    let res = wallet_change_password(&session, old_password, new_password)?;
+
+    // The wallet must be re-opened with the new password
+    let config = session.config.clone();
+    drop(session);
+    reopen_session(handle, config, new_password)?;
+    Ok(res)
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_change_password(
+    handle: u64,
+    old_password: *const c_char,
+    new_password: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        session_change_password(
+            handle,
+            &cstr_to_secret(old_password, "old_password")?,
+            &cstr_to_secret(new_password, "new_password")?,
+        )
+    });
     unsafe { result_to_cstr(res, error) }
 }
 
 fn wallet_restore(
-    json_cfg: &str,
+    session: &WalletSession,
     start_index: u64,
     batch_size: u64,
 ) -> Result<String, Error> {
-    let config = MobileWalletCfg::from_str(json_cfg)?;
-    let wallet_config = new_wallet_config(config.clone())?;
-    let node_api_secret = get_first_line(wallet_config.node_api_secret_path.clone());
-    let node_client = HTTPNodeClient::new(&wallet_config.check_node_api_http_addr, node_api_secret);
-    let wallet = instantiate_wallet(wallet_config, node_client, config.password.as_str(), &config.account)?;
-    let api = Owner::new(wallet.clone());
+    let api = Owner::new(session.wallet.clone());
 
     let (highest_index, last_retrieved_index, num_of_found) = api
         .restore_batch(start_index, batch_size)
@@ -264,26 +669,38 @@ fn wallet_restore(
 #[no_mangle]
 pub extern "C" fn grin_wallet_restore(
     json_cfg: *const c_char,
+    password: *const c_char,
     start_index: u64,
     batch_size: u64,
     error: *mut u8,
 ) -> *const c_char {
-    let res = wallet_restore(
-        &cstr_to_str(json_cfg),
-        start_index,
-        batch_size,
-    );
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password)
+            .and_then(|session| wallet_restore(&session, start_index, batch_size))
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_restore(
+    handle: u64,
+    start_index: u64,
+    batch_size: u64,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        with_session(handle, None, |session| wallet_restore(session, start_index, batch_size))
+    });
     unsafe { result_to_cstr(res, error) }
 }
 
 fn wallet_check(
-    json_cfg: &str,
+    session: &WalletSession,
     start_index: u64,
     batch_size: u64,
     update_outputs: bool,
 ) -> Result<String, Error> {
-    let wallet = get_wallet_instance(MobileWalletCfg::from_str(json_cfg)?)?;
-    let api = Owner::new(wallet);
+    let api = Owner::new(session.wallet.clone());
     let (highest_index, last_retrieved_index) = api
         .check_repair_batch(true, start_index, batch_size, update_outputs)
         .map_err(|e| Error::from(e))?;
@@ -298,377 +715,1909 @@ fn wallet_check(
 #[no_mangle]
 pub extern "C" fn grin_wallet_check(
     json_cfg: *const c_char,
+    password: *const c_char,
     start_index: u64,
     batch_size: u64,
     update_outputs: bool,
     error: *mut u8,
 ) -> *const c_char {
-    let res = wallet_check(
-        &cstr_to_str(json_cfg),
-        start_index,
-        batch_size,
-        update_outputs,
-    );
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password).and_then(|session| {
+            wallet_check(&session, start_index, batch_size, update_outputs)
+        })
+    });
     unsafe { result_to_cstr(res, error) }
 }
 
-fn get_wallet_mnemonic(json_cfg: &str) -> Result<String, Error> {
-    let config = MobileWalletCfg::from_str(json_cfg)?;
+#[no_mangle]
+pub extern "C" fn grin_session_check(
+    handle: u64,
+    start_index: u64,
+    batch_size: u64,
+    update_outputs: bool,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        with_session(handle, None, |session| {
+            wallet_check(session, start_index, batch_size, update_outputs)
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+fn get_wallet_mnemonic(config: &MobileWalletCfg, password: &str) -> Result<String, Error> {
+    verify_password(config, password)?;
     let wallet_config = new_wallet_config(config.clone())?;
-    let seed = WalletSeed::from_file(&wallet_config.data_file_dir, config.password.as_str())?;
+    let seed = WalletSeed::from_file(&wallet_config.data_file_dir, password)?;
     seed.to_mnemonic()
 }
 
 #[no_mangle]
 pub extern "C" fn grin_get_wallet_mnemonic(
     json_cfg: *const c_char,
+    password: *const c_char,
     error: *mut u8,
 ) -> *const c_char {
-    let res = get_wallet_mnemonic(&cstr_to_str(json_cfg));
+    let res = catch_panic(|| {
+        MobileWalletCfg::from_str(&cstr_to_str(json_cfg, "json_cfg")?).and_then(|config| {
+            get_wallet_mnemonic(&config, &cstr_to_secret(password, "password")?)
+        })
+    });
     unsafe { result_to_cstr(res, error) }
 }
 
-fn get_wallet_instance(
-    config: MobileWalletCfg,
-) -> Result<Arc<Mutex<WalletInst<impl NodeClient, ExtKeychain>>>, Error> {
-    let wallet_config = new_wallet_config(config.clone())?;
-    let node_api_secret = get_first_line(wallet_config.node_api_secret_path.clone());
-    let node_client = HTTPNodeClient::new(&wallet_config.check_node_api_http_addr, node_api_secret);
-
-    instantiate_wallet(
-        wallet_config,
-        node_client,
-        config.password.as_str(),
-        config.account.as_str(),
-    )
-}
-
-fn get_balance(json_cfg: &str) -> Result<(bool, String), Error> {
-    let wallet = get_wallet_instance(MobileWalletCfg::from_str(json_cfg)?)?;
-    let api = Owner::new(wallet);
-    let (validated, wallet_info) = api.retrieve_summary_info(true, MINIMUM_CONFIRMATIONS)?;
-    Ok((validated, serde_json::to_string(&wallet_info).unwrap()))
+#[no_mangle]
+pub extern "C" fn grin_session_get_wallet_mnemonic(
+    handle: u64,
+    password: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        with_session(handle, None, |session| {
+            get_wallet_mnemonic(&session.config, &cstr_to_secret(password, "password")?)
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
 }
 
 #[no_mangle]
-pub extern "C" fn grin_get_balance(
+pub extern "C" fn grin_wallet_open(
     json_cfg: *const c_char,
+    password: *const c_char,
+    passphrase: *const c_char,
     error: *mut u8,
 ) -> *const c_char {
-    let res = get_balance(&cstr_to_str(json_cfg));
-    unsafe { result2_to_cstr(res, error) }
+    let res = catch_panic(|| {
+        let passphrase = cstr_to_opt_secret(passphrase, "passphrase")?;
+        open_session(
+            MobileWalletCfg::from_str(&cstr_to_str(json_cfg, "json_cfg")?)?,
+            &cstr_to_secret(password, "password")?,
+            passphrase.as_ref().map(|p| p.as_str()),
+        )
+        .map(|handle| handle.to_string())
+    });
+    unsafe { result_to_cstr(res, error) }
 }
 
-fn tx_retrieve(json_cfg: &str, tx_slate_id: &str) -> Result<String, Error> {
-    let wallet = get_wallet_instance(MobileWalletCfg::from_str(json_cfg)?)?;
-    let api = Owner::new(wallet);
-    let uuid = Uuid::parse_str(tx_slate_id).map_err(|e| ErrorKind::GenericError(e.to_string()))?;
-    let txs = api.retrieve_txs(true, None, Some(uuid))?;
-    Ok(serde_json::to_string(&txs).unwrap())
+#[no_mangle]
+pub extern "C" fn grin_wallet_lock(
+    handle: u64,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        let session = get_session(handle)?;
+        session.lock();
+        Ok("OK".to_owned())
+    });
+    unsafe { result_to_cstr(res, error) }
 }
 
 #[no_mangle]
-pub extern "C" fn grin_tx_retrieve(
-    json_cfg: *const c_char,
-    tx_slate_id: *const c_char,
+pub extern "C" fn grin_wallet_unlock(
+    handle: u64,
+    password: *const c_char,
     error: *mut u8,
 ) -> *const c_char {
-    let res = tx_retrieve(
-        &cstr_to_str(json_cfg),
-        &cstr_to_str(tx_slate_id),
-    );
+    let res = catch_panic(|| {
+        let session = get_session(handle)?;
+        session.unlock(&cstr_to_secret(password, "password")?)?;
+        Ok("OK".to_owned())
+    });
     unsafe { result_to_cstr(res, error) }
 }
 
-fn txs_retrieve(json_cfg: &str) -> Result<String, Error> {
-    let wallet = get_wallet_instance(MobileWalletCfg::from_str(json_cfg)?)?;
-    let api = Owner::new(wallet);
-
-    match api.retrieve_txs(true, None, None) {
-        Ok(txs) => Ok(serde_json::to_string(&txs).unwrap()),
-        Err(e) => Err(Error::from(e)),
-    }
+#[no_mangle]
+pub extern "C" fn grin_wallet_is_locked(
+    handle: u64,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        let session = get_session(handle)?;
+        Ok(serde_json::to_string(&session.is_locked()).unwrap())
+    });
+    unsafe { result_to_cstr(res, error) }
 }
 
 #[no_mangle]
-pub extern "C" fn grin_txs_retrieve(
-    state_json: *const c_char,
+pub extern "C" fn grin_wallet_close(
+    handle: u64,
     error: *mut u8,
 ) -> *const c_char {
-    let res = txs_retrieve(&cstr_to_str(state_json));
+    let res = catch_panic(|| close_session(handle).map(|_| "OK".to_owned()));
     unsafe { result_to_cstr(res, error) }
 }
 
-fn outputs_retrieve(json_cfg: &str, tx_id: Option<u32>) -> Result<String, Error> {
-    let wallet = get_wallet_instance(MobileWalletCfg::from_str(json_cfg)?)?;
-    let api = Owner::new(wallet);
-    let outputs = api.retrieve_outputs(true, true, tx_id)?;
-    Ok(serde_json::to_string(&outputs).unwrap())
+fn accounts_list(session: &WalletSession) -> Result<String, Error> {
+    let api = Owner::new(session.wallet.clone());
+    let accounts = api.accounts()?;
+    Ok(serde_json::to_string(&accounts).unwrap())
 }
 
 #[no_mangle]
-pub extern "C" fn grin_output_retrieve(
+pub extern "C" fn grin_accounts_list(
     json_cfg: *const c_char,
-    tx_id: u32,
+    password: *const c_char,
     error: *mut u8,
 ) -> *const c_char {
-    let res = outputs_retrieve(&cstr_to_str(json_cfg), Some(tx_id));
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password)
+            .and_then(|session| accounts_list(&session))
+    });
     unsafe { result_to_cstr(res, error) }
 }
 
 #[no_mangle]
-pub extern "C" fn grin_outputs_retrieve(
-    json_cfg: *const c_char,
+pub extern "C" fn grin_session_accounts_list(
+    handle: u64,
     error: *mut u8,
 ) -> *const c_char {
-    let res = outputs_retrieve(&cstr_to_str(json_cfg), None);
+    let res = catch_panic(|| with_session_read(handle, None, accounts_list));
     unsafe { result_to_cstr(res, error) }
 }
 
-fn init_send_tx(
-    json_cfg: &str,
-    amount: u64,
-    selection_strategy: &str,
-    target_slate_version: Option<u16>,
-    message: &str,
-) -> Result<String, Error> {
-    let wallet = get_wallet_instance(MobileWalletCfg::from_str(json_cfg)?)?;
-    let api = Owner::new(wallet);
-    let tx_args = InitTxArgs {
-        src_acct_name: None,
-        amount,
-        minimum_confirmations: MINIMUM_CONFIRMATIONS,
-        max_outputs: 500,
-        num_change_outputs: 1,
-        selection_strategy: selection_strategy.to_string(),
-        message: Some(message.to_string()),
-        target_slate_version,
-        estimate_only: None,
-        send_args: None,
-    };
-    let slate = api.init_send_tx(tx_args)?;
-    api.tx_lock_outputs(&slate, 0)?;
-    Ok(serde_json::to_string(&slate).expect("fail to serialize slate to json string"))
+fn account_create(session: &WalletSession, label: &str) -> Result<String, Error> {
+    if label.trim().is_empty() {
+        return Err(ErrorKind::ArgumentError("account label is empty".to_owned()).into());
+    }
+    let api = Owner::new(session.wallet.clone());
+    let path = api.create_account_path(label)?;
+    Ok(serde_json::to_string(&AcctPathMapping {
+        label: label.to_owned(),
+        path,
+    })
+    .unwrap())
 }
 
 #[no_mangle]
-pub extern "C" fn grin_init_tx(
+pub extern "C" fn grin_account_create(
     json_cfg: *const c_char,
-    amount: u64,
-    selection_strategy: *const c_char,
-    target_slate_version: i16,
-    message: *const c_char,
+    password: *const c_char,
+    label: *const c_char,
     error: *mut u8,
 ) -> *const c_char {
-    let mut slate_version: Option<u16> = None;
-    if target_slate_version >= 0 {
-        slate_version = Some(target_slate_version as u16);
-    }
-
-    let res = init_send_tx(
-        &cstr_to_str(json_cfg),
-        amount,
-        &cstr_to_str(selection_strategy),
-        slate_version,
-        &cstr_to_str(message),
-    );
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password)
+            .and_then(|session| account_create(&session, &cstr_to_str(label, "label")?))
+    });
     unsafe { result_to_cstr(res, error) }
 }
 
-fn listen(
-    json_cfg: &str,
-) -> Result<String, Error> {
-    let config = MobileWalletCfg::from_str(json_cfg)?;
-    let wallet = get_wallet_instance(config.clone())?;
-
-    // The streaming channel between 'grinrelay_listener' and 'foreign_listener'
-    let (relay_tx_as_payee, relay_rx) = channel();
-
-    // Start a Grin Relay service firstly
-    let grinrelay_listener = grinrelay_listener(
-        wallet.clone(),
-        config.grinrelay_config.clone().unwrap_or_default(),
-        None,
-        Some(relay_tx_as_payee),
-    )?;
-
-    let _handle = thread::spawn(move || {
-        let api = Foreign::new(wallet, None);
-        loop {
-            match relay_rx.try_recv() {
-                Ok((addr, slate)) => {
-                    let _slate_id = slate.id;
-                    if api.verify_slate_messages(&slate).is_ok() {
-                        let slate_rx = api.receive_tx(&slate, Some(&config.account), None);
-                        if let Ok(slate_rx) = slate_rx {
-                            let versioned_slate =
-                                VersionedSlate::into_version(slate_rx.clone(), SlateVersion::V2);
-                            let res = grinrelay_listener.publish(&versioned_slate, &addr.to_owned());
-                            match res {
-                                Ok(_) => {
-//                                    info!(
-//                                        "Slate [{}] sent back to {} successfully",
-//                                        slate_id.to_string().bright_green(),
-//                                        addr.bright_green(),
-//                                    );
-                                }
-                                Err(_e) => {
-//                                    error!(
-//                                        "Slate [{}] fail to sent back to {} for {}",
-//                                        slate_id.to_string().bright_green(),
-//                                        addr.bright_green(),
-//                                        e,
-//                                    );
-                                }
-                            }
-                        }
-                    }
-                }
-                Err(TryRecvError::Disconnected) => break,
-                Err(TryRecvError::Empty) => {}
-            }
-            thread::sleep(Duration::from_millis(100));
-        }
+#[no_mangle]
+pub extern "C" fn grin_session_account_create(
+    handle: u64,
+    label: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        let label = cstr_to_str(label, "label")?;
+        with_session(handle, None, |session| account_create(session, &label))
     });
+    unsafe { result_to_cstr(res, error) }
+}
 
-//    if handle.is_err() {
-//        Err(ErrorKind::GenericError("Listen thread fail to start".to_string()).into())?
-//    }
+fn get_balance(session: &WalletSession) -> Result<(bool, String), Error> {
+    let api = Owner::new(session.wallet.clone());
+    let (validated, wallet_info) =
+        api.retrieve_summary_info(true, session.config.minimum_confirmations)?;
+    Ok((validated, serde_json::to_string(&wallet_info).unwrap()))
+}
+
+#[no_mangle]
+pub extern "C" fn grin_get_balance(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password)
+            .and_then(|session| get_balance(&session))
+    });
+    unsafe { result2_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_get_balance(
+    handle: u64,
+    account: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        let account = cstr_to_opt_str(account)?;
+        with_session_read(handle, account.as_ref().map(|a| a.as_str()), get_balance)
+    });
+    unsafe { result2_to_cstr(res, error) }
+}
+
+fn tx_retrieve(session: &WalletSession, tx_slate_id: &str) -> Result<String, Error> {
+    let api = Owner::new(session.wallet.clone());
+    let uuid = Uuid::parse_str(tx_slate_id).map_err(|e| ErrorKind::ArgumentError(e.to_string()))?;
+    let (validated, txs) = api.retrieve_txs(true, None, Some(uuid))?;
+    let txs = with_tx_metadata(&session.config, txs)?;
+    Ok(serde_json::to_string(&(validated, txs)).unwrap())
+}
+
+#[no_mangle]
+pub extern "C" fn grin_tx_retrieve(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    tx_slate_id: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password)
+            .and_then(|session| tx_retrieve(&session, &cstr_to_str(tx_slate_id, "tx_slate_id")?))
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_tx_retrieve(
+    handle: u64,
+    tx_slate_id: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        with_session_read(handle, None, |session| {
+            tx_retrieve(session, &cstr_to_str(tx_slate_id, "tx_slate_id")?)
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+fn txs_retrieve(session: &WalletSession) -> Result<String, Error> {
+    let api = Owner::new(session.wallet.clone());
+
+    match api.retrieve_txs(true, None, None) {
+        Ok((validated, txs)) => {
+            let txs = with_tx_metadata(&session.config, txs)?;
+            Ok(serde_json::to_string(&(validated, txs)).unwrap())
+        }
+        Err(e) => Err(Error::from(e)),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_txs_retrieve(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password)
+            .and_then(|session| txs_retrieve(&session))
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_txs_retrieve(
+    handle: u64,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| with_session_read(handle, None, txs_retrieve));
+    unsafe { result_to_cstr(res, error) }
+}
+
+fn txs_query(session: &WalletSession, query: &TxQuery) -> Result<String, Error> {
+    let api = Owner::new(session.wallet.clone());
+    let (validated, txs) = api.retrieve_txs(true, None, None)?;
+    let (total, txs) = query.apply(txs);
+    let page = TxPage {
+        validated,
+        total,
+        txs: with_tx_metadata(&session.config, txs)?,
+    };
+    Ok(serde_json::to_string(&page).unwrap())
+}
+
+#[no_mangle]
+pub extern "C" fn grin_txs_query(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    filter_json: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        let query = TxQuery::from_str(cstr_to_opt_str(filter_json)?)?;
+        session_from_json(json_cfg, password)
+            .and_then(|session| txs_query(&session, &query))
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_txs_query(
+    handle: u64,
+    filter_json: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        let query = TxQuery::from_str(cstr_to_opt_str(filter_json)?)?;
+        with_session_read(handle, None, |session| txs_query(session, &query))
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+fn txs_export(
+    session: &WalletSession,
+    format: &str,
+    path: &str,
+    date_range: &DateRange,
+) -> Result<String, Error> {
+    let format = ExportFormat::from_str(format)?;
+    let count = export_txs(
+        &session.config,
+        session.wallet.clone(),
+        format,
+        Path::new(path),
+        date_range,
+    )?;
+    Ok(serde_json::to_string(&count).unwrap())
+}
+
+#[no_mangle]
+pub extern "C" fn grin_export_txs(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    format: *const c_char,
+    path: *const c_char,
+    date_range: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        let date_range = DateRange::from_str(cstr_to_opt_str(date_range)?)?;
+        session_from_json(json_cfg, password).and_then(|session| {
+            txs_export(
+                &session,
+                &cstr_to_str(format, "format")?,
+                &cstr_to_str(path, "path")?,
+                &date_range,
+            )
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_export_txs(
+    handle: u64,
+    format: *const c_char,
+    path: *const c_char,
+    date_range: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        let date_range = DateRange::from_str(cstr_to_opt_str(date_range)?)?;
+        with_session_read(handle, None, |session| {
+            txs_export(
+                session,
+                &cstr_to_str(format, "format")?,
+                &cstr_to_str(path, "path")?,
+                &date_range,
+            )
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+fn tx_metadata_set(
+    session: &WalletSession,
+    tx_slate_id: &str,
+    metadata_json: &str,
+) -> Result<String, Error> {
+    let uuid = Uuid::parse_str(tx_slate_id).map_err(|e| ErrorKind::ArgumentError(e.to_string()))?;
+    let metadata: TxMetadata = serde_json::from_str(metadata_json)
+        .map_err(|e| Error::from(ErrorKind::ArgumentError(e.to_string())))?;
+    let api = Owner::new(session.wallet.clone());
+    let (_, txs) = api.retrieve_txs(false, None, Some(uuid))?;
+    if txs.is_empty() {
+        return Err(Error::from(ErrorKind::ArgumentError(format!(
+            "unknown tx: {}",
+            tx_slate_id
+        ))));
+    }
+    set_tx_metadata(&session.config, &uuid.to_string(), metadata)?;
+    Ok("OK".to_owned())
+}
+
+#[no_mangle]
+pub extern "C" fn grin_tx_metadata_set(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    tx_slate_id: *const c_char,
+    metadata_json: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password).and_then(|session| {
+            tx_metadata_set(
+                &session,
+                &cstr_to_str(tx_slate_id, "tx_slate_id")?,
+                &cstr_to_str(metadata_json, "metadata_json")?,
+            )
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_tx_metadata_set(
+    handle: u64,
+    tx_slate_id: *const c_char,
+    metadata_json: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        with_session(handle, None, |session| {
+            tx_metadata_set(
+                session,
+                &cstr_to_str(tx_slate_id, "tx_slate_id")?,
+                &cstr_to_str(metadata_json, "metadata_json")?,
+            )
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+fn tx_metadata_get(session: &WalletSession, tx_slate_id: &str) -> Result<String, Error> {
+    let uuid = Uuid::parse_str(tx_slate_id).map_err(|e| ErrorKind::ArgumentError(e.to_string()))?;
+    let metadata = tx_metadata(&session.config, &uuid.to_string())?;
+    Ok(serde_json::to_string(&metadata).unwrap())
+}
+
+#[no_mangle]
+pub extern "C" fn grin_tx_metadata_get(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    tx_slate_id: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password).and_then(|session| {
+            tx_metadata_get(&session, &cstr_to_str(tx_slate_id, "tx_slate_id")?)
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_tx_metadata_get(
+    handle: u64,
+    tx_slate_id: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        with_session_read(handle, None, |session| {
+            tx_metadata_get(session, &cstr_to_str(tx_slate_id, "tx_slate_id")?)
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+fn tx_metadata_search(session: &WalletSession, text: &str) -> Result<String, Error> {
+    let entries = search_tx_metadata(&session.config, text)?;
+    Ok(serde_json::to_string(&entries).unwrap())
+}
+
+#[no_mangle]
+pub extern "C" fn grin_tx_metadata_search(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    text: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password)
+            .and_then(|session| tx_metadata_search(&session, &cstr_to_str(text, "text")?))
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_tx_metadata_search(
+    handle: u64,
+    text: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        with_session_read(handle, None, |session| {
+            tx_metadata_search(session, &cstr_to_str(text, "text")?)
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+fn outputs_retrieve(session: &WalletSession, tx_id: Option<u32>) -> Result<String, Error> {
+    let api = Owner::new(session.wallet.clone());
+    let outputs = api.retrieve_outputs(true, true, tx_id)?;
+    Ok(serde_json::to_string(&outputs).unwrap())
+}
+
+#[no_mangle]
+pub extern "C" fn grin_output_retrieve(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    tx_id: u32,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password)
+            .and_then(|session| outputs_retrieve(&session, Some(tx_id)))
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_output_retrieve(
+    handle: u64,
+    tx_id: u32,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        with_session_read(handle, None, |session| outputs_retrieve(session, Some(tx_id)))
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_outputs_retrieve(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password)
+            .and_then(|session| outputs_retrieve(&session, None))
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_outputs_retrieve(
+    handle: u64,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        with_session_read(handle, None, |session| outputs_retrieve(session, None))
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+fn outputs_frozen(session: &WalletSession) -> Result<String, Error> {
+    let commits = frozen_outputs(&session.config)?;
+    Ok(serde_json::to_string(&commits).unwrap())
+}
+
+#[no_mangle]
+pub extern "C" fn grin_outputs_frozen(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password)
+            .and_then(|session| outputs_frozen(&session))
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_outputs_frozen(
+    handle: u64,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| with_session_read(handle, None, outputs_frozen));
+    unsafe { result_to_cstr(res, error) }
+}
+
+fn output_freeze(session: &WalletSession, commit: &str, frozen: bool) -> Result<String, Error> {
+    set_frozen(&session.config, &session.wallet, commit, frozen)?;
+    Ok("OK".to_owned())
+}
+
+#[no_mangle]
+pub extern "C" fn grin_output_freeze(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    commit: *const c_char,
+    frozen: bool,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password).and_then(|session| {
+            output_freeze(&session, &cstr_to_str(commit, "commit")?, frozen)
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_output_freeze(
+    handle: u64,
+    commit: *const c_char,
+    frozen: bool,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        with_session(handle, None, |session| {
+            output_freeze(session, &cstr_to_str(commit, "commit")?, frozen)
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+fn init_send_tx(
+    session: &WalletSession,
+    amount: u64,
+    selection_strategy: &str,
+    target_slate_version: Option<u16>,
+    message: Option<String>,
+    options: &SendOptions,
+) -> Result<String, Error> {
+    let api = Owner::new(session.wallet.clone());
+    let tx_args = options.init_tx_args(
+        &session.config,
+        amount,
+        selection_strategy,
+        target_slate_version,
+        message,
+    );
+    let slate = options.with_coin_control(session, || {
+        let slate = api.init_send_tx(tx_args)?;
+        api.tx_lock_outputs(&slate, 0)?;
+        Ok(slate)
+    })?;
+    Ok(serde_json::to_string(&slate).expect("fail to serialize slate to json string"))
+}
+
+#[no_mangle]
+pub extern "C" fn grin_init_tx(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    amount: u64,
+    selection_strategy: *const c_char,
+    target_slate_version: i16,
+    message: *const c_char,
+    send_options: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let mut slate_version: Option<u16> = None;
+    if target_slate_version >= 0 {
+        slate_version = Some(target_slate_version as u16);
+    }
+
+    let res = catch_panic(|| {
+        let options = SendOptions::from_str(cstr_to_opt_str(send_options)?)?;
+        session_from_json(json_cfg, password).and_then(|session| {
+            init_send_tx(
+                &session,
+                amount,
+                &cstr_to_str(selection_strategy, "selection_strategy")?,
+                slate_version,
+                cstr_to_opt_str(message)?,
+                &options,
+            )
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_init_tx(
+    handle: u64,
+    account: *const c_char,
+    amount: u64,
+    selection_strategy: *const c_char,
+    target_slate_version: i16,
+    message: *const c_char,
+    send_options: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let mut slate_version: Option<u16> = None;
+    if target_slate_version >= 0 {
+        slate_version = Some(target_slate_version as u16);
+    }
+
+    let res = catch_panic(|| {
+        let options = SendOptions::from_str(cstr_to_opt_str(send_options)?)?;
+        let account = cstr_to_opt_str(account)?;
+        with_session(handle, account.as_ref().map(|a| a.as_str()), |session| {
+            init_send_tx(
+                session,
+                amount,
+                &cstr_to_str(selection_strategy, "selection_strategy")?,
+                slate_version,
+                cstr_to_opt_str(message)?,
+                &options,
+            )
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+/// Fee estimate of a send, as reported to the app
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FeeEstimate {
+    selection_strategy: String,
+    amount: u64,
+    fee: u64,
+    /// The amount plus the fee
+    total: u64,
+    inputs: usize,
+    outputs: usize,
+    /// The total value of the inputs, locked until the transaction is confirmed or cancelled
+    locked: u64,
+    change: u64,
+}
+
+fn estimate_fee(
+    session: &WalletSession,
+    amount: u64,
+    selection_strategy: Option<&str>,
+    options: &SendOptions,
+) -> Result<String, Error> {
+    // The inputs are counted from the outputs of the active account
+    if options.src_acct_name.is_some() {
+        return Err(Error::from(ErrorKind::ArgumentError(
+            "src_acct_name is not supported by the fee estimate, select the account instead"
+                .to_owned(),
+        )));
+    }
+    // Excluding outputs from the coin selection marks them locked in the wallet database
+    // for the time of the estimate, as for a send, which a locked session doesn't allow.
+    if options.input_commits.is_some() || !frozen_outputs(&session.config)?.is_empty() {
+        session.check_access(Access::Full)?;
+    }
+    let strategies = match selection_strategy {
+        _ if options.input_commits.is_some() => vec!["all"],
+        Some(strategy) => vec![strategy],
+        None => vec!["all", "smallest"],
+    };
+    let api = Owner::new(session.wallet.clone());
+    let mut estimates = vec![];
+    for strategy in strategies {
+        if strategy != "all" && strategy != "smallest" {
+            return Err(Error::from(ErrorKind::ArgumentError(format!(
+                "unknown selection strategy: {}",
+                strategy
+            ))));
+        }
+        let mut tx_args = options.init_tx_args(&session.config, amount, strategy, None, None);
+        tx_args.estimate_only = Some(true);
+        // With 'estimate_only', the slate amount is the total value of the selected inputs
+        let (slate, inputs) = options.with_coin_control(session, || {
+            let slate = api.init_send_tx(tx_args)?;
+            let inputs = match options.input_commits {
+                Some(ref input_commits) => input_commits.len(),
+                None => count_inputs(
+                    session,
+                    slate.amount,
+                    options.minimum_confirmations(&session.config),
+                )?,
+            };
+            Ok((slate, inputs))
+        })?;
+        let locked = slate.amount;
+        let change = locked.saturating_sub(amount + slate.fee);
+        estimates.push(FeeEstimate {
+            selection_strategy: strategy.to_string(),
+            amount,
+            fee: slate.fee,
+            total: amount + slate.fee,
+            inputs,
+            outputs: if change > 0 {
+                1 + options.num_change_outputs()
+            } else {
+                1
+            },
+            locked,
+            change,
+        });
+    }
+    Ok(serde_json::to_string(&estimates).unwrap())
+}
+
+/// Number of the inputs selected for a total value, since the estimate of the wallet
+/// only returns their value. The wallet selects the smallest spendable outputs firstly,
+/// and with the "all" strategy the total is the value of all of them.
+fn count_inputs(
+    session: &WalletSession,
+    total: u64,
+    minimum_confirmations: u64,
+) -> Result<usize, Error> {
+    let api = Owner::new(session.wallet.clone());
+    let height = api.node_height()?.height;
+    let (_, outputs) = api.retrieve_outputs(false, false, None)?;
+    let mut values: Vec<u64> = outputs
+        .iter()
+        .filter(|o| o.output.eligible_to_spend(height, minimum_confirmations))
+        .map(|o| o.output.value)
+        .collect();
+    values.sort();
+
+    let mut selected = 0;
+    let mut count = 0;
+    for value in values {
+        if selected >= total {
+            break;
+        }
+        selected += value;
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[no_mangle]
+pub extern "C" fn grin_estimate_fee(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    amount: u64,
+    selection_strategy: *const c_char,
+    send_options: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        let options = SendOptions::from_str(cstr_to_opt_str(send_options)?)?;
+        let selection_strategy = cstr_to_opt_str(selection_strategy)?;
+        session_from_json(json_cfg, password).and_then(|session| {
+            estimate_fee(
+                &session,
+                amount,
+                selection_strategy.as_ref().map(|s| s.as_str()),
+                &options,
+            )
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_estimate_fee(
+    handle: u64,
+    account: *const c_char,
+    amount: u64,
+    selection_strategy: *const c_char,
+    send_options: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        let options = SendOptions::from_str(cstr_to_opt_str(send_options)?)?;
+        let account = cstr_to_opt_str(account)?;
+        let selection_strategy = cstr_to_opt_str(selection_strategy)?;
+        with_session_read(handle, account.as_ref().map(|a| a.as_str()), |session| {
+            estimate_fee(
+                session,
+                amount,
+                selection_strategy.as_ref().map(|s| s.as_str()),
+                &options,
+            )
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+/// Spend the spendable outputs to the wallet itself, into `num_outputs` outputs of
+/// about the same value, or only estimate the fee of it.
+///
+/// The smallest outputs are spent firstly, up to the `max_outputs` of the options.
+fn self_spend(
+    session: &WalletSession,
+    num_outputs: usize,
+    estimate_only: bool,
+    options: &SendOptions,
+) -> Result<String, Error> {
+    // The outputs are spent from the active account, and received back to it
+    if options.src_acct_name.is_some() {
+        return Err(Error::from(ErrorKind::ArgumentError(
+            "src_acct_name is not supported by a self spend, select the account instead"
+                .to_owned(),
+        )));
+    }
+    let config = &session.config;
+    let api = Owner::new(session.wallet.clone());
+    let max_outputs = options.max_outputs.unwrap_or(MAX_SEND_OUTPUTS) as usize;
+
+    let res = options.with_coin_control(session, || {
+        let height = api.node_height()?.height;
+        let (_, outputs) = api.retrieve_outputs(false, true, None)?;
+        let minimum_confirmations = options.minimum_confirmations(config);
+        let mut values: Vec<u64> = outputs
+            .iter()
+            .filter(|o| o.output.eligible_to_spend(height, minimum_confirmations))
+            .map(|o| o.output.value)
+            .collect();
+        values.sort();
+        values.truncate(max_outputs);
+        if values.is_empty() || (num_outputs == 1 && values.len() < 2) {
+            return Err(Error::from(ErrorKind::ArgumentError(format!(
+                "not enough spendable outputs: {}",
+                values.len()
+            ))));
+        }
+
+        let locked: u64 = values.iter().sum();
+        let fee = tx_fee(values.len(), num_outputs, 1, None);
+        if locked <= fee + num_outputs as u64 {
+            return Err(Error::from(ErrorKind::ArgumentError(format!(
+                "the spendable outputs value {} doesn't cover the fee {}",
+                locked, fee
+            ))));
+        }
+        // The wallet receives one output of this amount, the others are the change.
+        let amount = (locked - fee) / num_outputs as u64;
+        let estimate = FeeEstimate {
+            selection_strategy: "all".to_owned(),
+            amount,
+            fee,
+            total: amount + fee,
+            inputs: values.len(),
+            outputs: num_outputs,
+            locked,
+            change: locked - fee - amount,
+        };
+        if estimate_only {
+            return Ok((estimate, None));
+        }
+
+        let mut args = options.init_tx_args(config, amount, "all", None, None);
+        args.num_change_outputs = num_outputs as u32 - 1;
+        let slate = api.init_send_tx(args)?;
+        api.tx_lock_outputs(&slate, 0)?;
+        Ok((estimate, Some(slate)))
+    })?;
+    let slate = match res {
+        (estimate, None) => return Ok(serde_json::to_string(&estimate).unwrap()),
+        (_, Some(slate)) => slate,
+    };
+
+    let res = Foreign::new(session.wallet.clone(), None)
+        .receive_tx(&slate, None, None)
+        .and_then(|slate| api.finalize_tx(&slate))
+        .and_then(|slate| {
+            api.post_tx(&slate.tx, false)?;
+            Ok(slate)
+        });
+    match res {
+        Ok(slate) => {
+            Ok(serde_json::to_string(&slate).expect("fail to serialize slate to json string"))
+        }
+        Err(e) => {
+            // The sent and the received entries have the same slate id, so they're
+            // cancelled one by one
+            let (_, txs) = api.retrieve_txs(false, None, Some(slate.id))?;
+            for tx in txs {
+                match tx.tx_type {
+                    TxLogEntryType::TxSent | TxLogEntryType::TxReceived => {
+                        api.cancel_tx(Some(tx.id), None)?
+                    }
+                    _ => {}
+                }
+            }
+            Err(Error::from(e))
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_consolidate_outputs(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    estimate_only: bool,
+    send_options: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        let options = SendOptions::from_str(cstr_to_opt_str(send_options)?)?;
+        session_from_json(json_cfg, password)
+            .and_then(|session| self_spend(&session, 1, estimate_only, &options))
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_consolidate_outputs(
+    handle: u64,
+    account: *const c_char,
+    estimate_only: bool,
+    send_options: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        let options = SendOptions::from_str(cstr_to_opt_str(send_options)?)?;
+        let account = cstr_to_opt_str(account)?;
+        with_session(handle, account.as_ref().map(|a| a.as_str()), |session| {
+            self_spend(session, 1, estimate_only, &options)
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+fn check_split_outputs(num_outputs: u32) -> Result<usize, Error> {
+    if num_outputs < 2 || num_outputs > MAX_CHANGE_OUTPUTS + 1 {
+        return Err(Error::from(ErrorKind::ArgumentError(format!(
+            "num_outputs {} is not in [2, {}]",
+            num_outputs,
+            MAX_CHANGE_OUTPUTS + 1
+        ))));
+    }
+    Ok(num_outputs as usize)
+}
+
+#[no_mangle]
+pub extern "C" fn grin_split_outputs(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    num_outputs: u32,
+    estimate_only: bool,
+    send_options: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        let options = SendOptions::from_str(cstr_to_opt_str(send_options)?)?;
+        let num_outputs = check_split_outputs(num_outputs)?;
+        session_from_json(json_cfg, password)
+            .and_then(|session| self_spend(&session, num_outputs, estimate_only, &options))
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_split_outputs(
+    handle: u64,
+    account: *const c_char,
+    num_outputs: u32,
+    estimate_only: bool,
+    send_options: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        let options = SendOptions::from_str(cstr_to_opt_str(send_options)?)?;
+        let num_outputs = check_split_outputs(num_outputs)?;
+        let account = cstr_to_opt_str(account)?;
+        with_session(handle, account.as_ref().map(|a| a.as_str()), |session| {
+            self_spend(session, num_outputs, estimate_only, &options)
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+fn listen(session: &WalletSession) -> Result<String, Error> {
+    start_listener(session)?;
+    Ok("OK".to_owned())
+}
+
+#[no_mangle]
+pub extern "C" fn grin_listen(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password)
+            .and_then(|session| listen(&session))
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_listen(
+    handle: u64,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| with_session(handle, None, listen));
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_listen_start(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password)
+            .and_then(|session| start_listener(&session))
+            .map(|id| id.to_string())
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_listen_start(
+    handle: u64,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        with_session(handle, None, start_listener).map(|id| id.to_string())
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_listen_stop(
+    listener_id: u64,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| stop_listener(listener_id).map(|_| "OK".to_owned()));
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_listen_status(
+    listener_id: u64,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        let status = listener_status(listener_id)?;
+        Ok(serde_json::to_string(&status).unwrap())
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_events_poll(error: *mut u8) -> *const c_char {
+    let res = catch_panic(|| Ok(serde_json::to_string(&poll_events()).unwrap()));
+    unsafe { result_to_cstr(res, error) }
+}
+
+fn send_tx_by_http(
+    session: &WalletSession,
+    amount: u64,
+    receiver_wallet_url: &str,
+    selection_strategy: &str,
+    target_slate_version: Option<u16>,
+    message: Option<String>,
+    options: &SendOptions,
+) -> Result<String, Error> {
+    let api = Owner::new(session.wallet.clone());
+    let args = options.init_tx_args(
+        &session.config,
+        amount,
+        selection_strategy,
+        target_slate_version,
+        message,
+    );
+    // The outputs are locked while the coin selection is serialized, then unlocked
+    // if the payee doesn't respond
+    let slate_r1 = options.with_coin_control(session, || {
+        let slate = api.init_send_tx(args)?;
+        api.tx_lock_outputs(&slate, 0)?;
+        Ok(slate)
+    })?;
+
+    let adapter = HTTPWalletCommAdapter::new();
+    let res = adapter
+        .send_tx_sync(receiver_wallet_url, &slate_r1)
+        .and_then(|slate| {
+            api.verify_slate_messages(&slate)?;
+            Ok(slate)
+        });
+    let slate = match res {
+        Ok(slate) => slate,
+        Err(e) => {
+            api.cancel_tx(None, Some(slate_r1.id))?;
+            return Err(e);
+        }
+    };
+
+    let finalized_slate = api.finalize_tx(&slate);
+    if finalized_slate.is_err() {
+        api.cancel_tx(None, Some(slate_r1.id))?;
+    }
+    let finalized_slate = finalized_slate?;
+
+    let res = api.post_tx(&finalized_slate.tx, false);
+    if res.is_err() {
+        api.cancel_tx(None, Some(slate_r1.id))?;
+        res?;
+    }
+
+    // No payment proof over http, a grin-wallet payee never signs one
+    let _ = set_counterparty(
+        &session.config,
+        &finalized_slate.id.to_string(),
+        receiver_wallet_url,
+    );
+
+    Ok(serde_json::to_string(&finalized_slate).expect("fail to serialize slate to json string"))
+}
+
+/// Publish a slate by the wallet listener, which is started if it isn't running yet.
+fn relay_publish(session: &WalletSession, addr: &str, slate: Slate) -> Result<(), Error> {
+    let listener_id = start_listener(session)?;
+    let timeout = session
+        .config
+        .grinrelay_ready_timeout_secs
+        .unwrap_or(DEFAULT_GRINRELAY_READY_TIMEOUT_SECS);
+    wait_ready(listener_id, Duration::from_secs(timeout))?;
+    publish_slate(listener_id, addr, slate)
+}
+
+fn send_tx_by_relay(
+    session: &WalletSession,
+    amount: u64,
+    receiver_addr: &str,
+    selection_strategy: &str,
+    target_slate_version: Option<u16>,
+    message: Option<String>,
+    options: &SendOptions,
+) -> Result<String, Error> {
+    let config = &session.config;
+    let api = Owner::new(session.wallet.clone());
+    let args = options.init_tx_args(
+        &session.config,
+        amount,
+        selection_strategy,
+        target_slate_version,
+        message,
+    );
+    let slate = options.with_coin_control(session, || {
+        let slate = api.init_send_tx(args)?;
+        api.tx_lock_outputs(&slate, 0)?;
+        Ok(slate)
+    })?;
+
+    // The listener publishes the slate, then finalizes and posts the payee's response
+    // in background, whenever it arrives.
+    let res = add_pending_send(
+        config,
+        PendingSend {
+            slate_id: slate.id.to_string(),
+            addr: receiver_addr.to_owned(),
+            amount,
+            created_at: unix_timestamp(),
+        },
+    )
+    .and_then(|_| relay_publish(session, receiver_addr, slate.clone()));
+    if let Err(e) = res {
+        let _ = take_pending_send(config, &slate.id.to_string());
+        api.cancel_tx(None, Some(slate.id))?;
+        return Err(e);
+    }
+    let _ = set_counterparty(config, &slate.id.to_string(), receiver_addr);
+
+    Ok(serde_json::to_string(&slate.id.to_string()).unwrap())
+}
+
+fn send_tx(
+    session: &WalletSession,
+    amount: u64,
+    receiver: &str,
+    selection_strategy: &str,
+    target_slate_version: Option<u16>,
+    message: Option<String>,
+    options: &SendOptions,
+) -> Result<String, Error> {
+    if receiver.starts_with("http://") || receiver.starts_with("https://") {
+        send_tx_by_http(
+            session,
+            amount,
+            receiver,
+            selection_strategy,
+            target_slate_version,
+            message,
+            options,
+        )
+    } else {
+        send_tx_by_relay(
+            session,
+            amount,
+            receiver,
+            selection_strategy,
+            target_slate_version,
+            message,
+            options,
+        )
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_send_tx(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    amount: u64,
+    receiver_addr_or_url: *const c_char,
+    selection_strategy: *const c_char,
+    target_slate_version: i16,
+    message: *const c_char,
+    send_options: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let mut slate_version: Option<u16> = None;
+    if target_slate_version >= 0 {
+        slate_version = Some(target_slate_version as u16);
+    }
+
+    let res = catch_panic(|| {
+        let options = SendOptions::from_str(cstr_to_opt_str(send_options)?)?;
+        session_from_json(json_cfg, password).and_then(|session| {
+            send_tx(
+                &session,
+                amount,
+                &cstr_to_str(receiver_addr_or_url, "receiver_addr_or_url")?,
+                &cstr_to_str(selection_strategy, "selection_strategy")?,
+                slate_version,
+                cstr_to_opt_str(message)?,
+                &options,
+            )
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_send_tx(
+    handle: u64,
+    account: *const c_char,
+    amount: u64,
+    receiver_addr_or_url: *const c_char,
+    selection_strategy: *const c_char,
+    target_slate_version: i16,
+    message: *const c_char,
+    send_options: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let mut slate_version: Option<u16> = None;
+    if target_slate_version >= 0 {
+        slate_version = Some(target_slate_version as u16);
+    }
+
+    let res = catch_panic(|| {
+        let options = SendOptions::from_str(cstr_to_opt_str(send_options)?)?;
+        let account = cstr_to_opt_str(account)?;
+        with_session(handle, account.as_ref().map(|a| a.as_str()), |session| {
+            send_tx(
+                session,
+                amount,
+                &cstr_to_str(receiver_addr_or_url, "receiver_addr_or_url")?,
+                &cstr_to_str(selection_strategy, "selection_strategy")?,
+                slate_version,
+                cstr_to_opt_str(message)?,
+                &options,
+            )
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+fn tx_payment_proof(session: &WalletSession, tx_slate_id: &str) -> Result<String, Error> {
+    let uuid = Uuid::parse_str(tx_slate_id).map_err(|e| ErrorKind::ArgumentError(e.to_string()))?;
+    let proof = payment_proof(&session.config, &uuid.to_string())?;
+    Ok(serde_json::to_string(&proof).unwrap())
+}
+
+#[no_mangle]
+pub extern "C" fn grin_tx_payment_proof(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    tx_slate_id: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password).and_then(|session| {
+            tx_payment_proof(&session, &cstr_to_str(tx_slate_id, "tx_slate_id")?)
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_tx_payment_proof(
+    handle: u64,
+    tx_slate_id: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        with_session_read(handle, None, |session| {
+            tx_payment_proof(session, &cstr_to_str(tx_slate_id, "tx_slate_id")?)
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+fn verify_proof(session: &WalletSession, proof_json: &str) -> Result<String, Error> {
+    let proof: PaymentProof = serde_json::from_str(proof_json).map_err(|e| {
+        Error::from(ErrorKind::ArgumentError(format!("invalid payment proof: {}", e)))
+    })?;
+    let verification = verify_payment_proof(&session.wallet, &proof)?;
+    Ok(serde_json::to_string(&verification).unwrap())
+}
+
+#[no_mangle]
+pub extern "C" fn grin_verify_payment_proof(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    proof_json: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password)
+            .and_then(|session| verify_proof(&session, &cstr_to_str(proof_json, "proof_json")?))
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_verify_payment_proof(
+    handle: u64,
+    proof_json: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        with_session_read(handle, None, |session| {
+            verify_proof(session, &cstr_to_str(proof_json, "proof_json")?)
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+fn payment_proof_address(session: &WalletSession) -> Result<String, Error> {
+    let address = proof_address(&session.wallet)?;
+    Ok(serde_json::to_string(&address).unwrap())
+}
+
+#[no_mangle]
+pub extern "C" fn grin_payment_proof_address(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password)
+            .and_then(|session| payment_proof_address(&session))
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_payment_proof_address(
+    handle: u64,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| with_session_read(handle, None, payment_proof_address));
+    unsafe { result_to_cstr(res, error) }
+}
+
+fn cancel_tx(session: &WalletSession, tx_slate_id: &str) -> Result<String, Error> {
+    let uuid = Uuid::parse_str(tx_slate_id).map_err(|e| ErrorKind::ArgumentError(e.to_string()))?;
+    let api = Owner::new(session.wallet.clone());
+    api.cancel_tx(None, Some(uuid))?;
     Ok("OK".to_owned())
 }
 
 #[no_mangle]
-pub extern "C" fn grin_listen(
-    json_cfg: *const c_char,
+pub extern "C" fn grin_cancel_tx(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    tx_slate_id: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password)
+            .and_then(|session| cancel_tx(&session, &cstr_to_str(tx_slate_id, "tx_slate_id")?))
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_cancel_tx(
+    handle: u64,
+    tx_slate_id: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        with_session(handle, None, |session| {
+            cancel_tx(session, &cstr_to_str(tx_slate_id, "tx_slate_id")?)
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+fn post_tx(session: &WalletSession, tx_slate_id: &str) -> Result<String, Error> {
+    let api = Owner::new(session.wallet.clone());
+    let uuid = Uuid::parse_str(tx_slate_id).map_err(|e| ErrorKind::ArgumentError(e.to_string()))?;
+    let (validated, txs) = api.retrieve_txs(true, None, Some(uuid))?;
+    let tx = txs.first().ok_or_else(|| {
+        LibError::TxNotExists.into_error(format!("transaction not found: {}", tx_slate_id))
+    })?;
+    if tx.confirmed {
+        return Err(Error::from(ErrorKind::GenericError(format!(
+            "Transaction already confirmed"
+        ))));
+    } else if !validated {
+        return Err(Error::from(ErrorKind::GenericError(format!(
+            "api.retrieve_txs not validated"
+        ))));
+    }
+
+    let stored_tx = api.get_stored_tx(tx)?;
+    match stored_tx {
+        Some(stored_tx) => {
+            api.post_tx(&stored_tx, false)?;
+            Ok("OK".to_owned())
+        }
+        None => Err(Error::from(ErrorKind::GenericError(format!(
+            "transaction data not found"
+        )))),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_post_tx(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    tx_slate_id: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password)
+            .and_then(|session| post_tx(&session, &cstr_to_str(tx_slate_id, "tx_slate_id")?))
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_post_tx(
+    handle: u64,
+    tx_slate_id: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        with_session(handle, None, |session| {
+            post_tx(session, &cstr_to_str(tx_slate_id, "tx_slate_id")?)
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+fn tx_file_send(
+    session: &WalletSession,
+    amount: u64,
+    selection_strategy: &str,
+    target_slate_version: Option<u16>,
+    message: Option<String>,
+    options: &SendOptions,
+    slate_file_path: &str,
+) -> Result<String, Error> {
+    let api = Owner::new(session.wallet.clone());
+    let tx_args = options.init_tx_args(
+        &session.config,
+        amount,
+        selection_strategy,
+        target_slate_version,
+        message,
+    );
+    let slate = options.with_coin_control(session, || {
+        let slate = api.init_send_tx(tx_args)?;
+        api.tx_lock_outputs(&slate, 0)?;
+        Ok(slate)
+    })?;
+    // The outputs are unlocked if the slate file can't be written
+    let adapter = FileWalletCommAdapter::new();
+    if let Err(e) = adapter.send_tx_async(slate_file_path, &slate) {
+        api.cancel_tx(None, Some(slate.id))?;
+        return Err(e);
+    }
+    Ok(serde_json::to_string(&slate).expect("fail to serialize slate to json string"))
+}
+
+#[no_mangle]
+pub extern "C" fn grin_tx_file_send(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    amount: u64,
+    selection_strategy: *const c_char,
+    target_slate_version: i16,
+    message: *const c_char,
+    slate_file_path: *const c_char,
+    send_options: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let mut slate_version: Option<u16> = None;
+    if target_slate_version >= 0 {
+        slate_version = Some(target_slate_version as u16);
+    }
+
+    let res = catch_panic(|| {
+        let options = SendOptions::from_str(cstr_to_opt_str(send_options)?)?;
+        session_from_json(json_cfg, password).and_then(|session| {
+            tx_file_send(
+                &session,
+                amount,
+                &cstr_to_str(selection_strategy, "selection_strategy")?,
+                slate_version,
+                cstr_to_opt_str(message)?,
+                &options,
+                &cstr_to_str(slate_file_path, "slate_file_path")?,
+            )
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_tx_file_send(
+    handle: u64,
+    account: *const c_char,
+    amount: u64,
+    selection_strategy: *const c_char,
+    target_slate_version: i16,
+    message: *const c_char,
+    slate_file_path: *const c_char,
+    send_options: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let mut slate_version: Option<u16> = None;
+    if target_slate_version >= 0 {
+        slate_version = Some(target_slate_version as u16);
+    }
+
+    let res = catch_panic(|| {
+        let options = SendOptions::from_str(cstr_to_opt_str(send_options)?)?;
+        let account = cstr_to_opt_str(account)?;
+        with_session(handle, account.as_ref().map(|a| a.as_str()), |session| {
+            tx_file_send(
+                session,
+                amount,
+                &cstr_to_str(selection_strategy, "selection_strategy")?,
+                slate_version,
+                cstr_to_opt_str(message)?,
+                &options,
+                &cstr_to_str(slate_file_path, "slate_file_path")?,
+            )
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+/// Receive a slate file, and write the response file if a path is given.
+fn tx_file_receive(
+    session: &WalletSession,
+    slate_file_path: &str,
+    message: Option<String>,
+    response_file_path: Option<&str>,
+) -> Result<String, Error> {
+    let api = Foreign::new(session.wallet.clone(), None);
+    let adapter = FileWalletCommAdapter::new();
+    let mut slate = adapter.receive_tx_async(&slate_file_path)?;
+    api.verify_slate_messages(&slate)?;
+    // Received into the active account
+    slate = api.receive_tx(&slate, None, message)?;
+    if let Some(response_file_path) = response_file_path {
+        if let Err(e) = adapter.send_tx_async(response_file_path, &slate) {
+            Owner::new(session.wallet.clone()).cancel_tx(None, Some(slate.id))?;
+            return Err(e);
+        }
+    }
+    Ok(serde_json::to_string(&slate).expect("fail to serialize slate to json string"))
+}
+
+#[no_mangle]
+pub extern "C" fn grin_tx_file_receive(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    slate_file_path: *const c_char,
+    message: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password).and_then(|session| {
+            tx_file_receive(
+                &session,
+                &cstr_to_str(slate_file_path, "slate_file_path")?,
+                cstr_to_opt_str(message)?,
+                None,
+            )
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_tx_file_receive(
+    handle: u64,
+    account: *const c_char,
+    slate_file_path: *const c_char,
+    message: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        let account = cstr_to_opt_str(account)?;
+        with_session(handle, account.as_ref().map(|a| a.as_str()), |session| {
+            tx_file_receive(
+                session,
+                &cstr_to_str(slate_file_path, "slate_file_path")?,
+                cstr_to_opt_str(message)?,
+                None,
+            )
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_tx_file_respond(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    slate_file_path: *const c_char,
+    message: *const c_char,
+    response_file_path: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password).and_then(|session| {
+            tx_file_receive(
+                &session,
+                &cstr_to_str(slate_file_path, "slate_file_path")?,
+                cstr_to_opt_str(message)?,
+                Some(&cstr_to_str(response_file_path, "response_file_path")?),
+            )
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_tx_file_respond(
+    handle: u64,
+    account: *const c_char,
+    slate_file_path: *const c_char,
+    message: *const c_char,
+    response_file_path: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        let account = cstr_to_opt_str(account)?;
+        with_session(handle, account.as_ref().map(|a| a.as_str()), |session| {
+            tx_file_receive(
+                session,
+                &cstr_to_str(slate_file_path, "slate_file_path")?,
+                cstr_to_opt_str(message)?,
+                Some(&cstr_to_str(response_file_path, "response_file_path")?),
+            )
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+fn tx_file_finalize(
+    session: &WalletSession,
+    slate_file_path: &str,
+) -> Result<String, Error> {
+    let api = Owner::new(session.wallet.clone());
+    let adapter = FileWalletCommAdapter::new();
+    let mut slate = adapter.receive_tx_async(slate_file_path)?;
+    api.verify_slate_messages(&slate)?;
+    slate = api.finalize_tx(&slate)?;
+    Ok(serde_json::to_string(&slate).expect("fail to serialize slate to json string"))
+}
+
+#[no_mangle]
+pub extern "C" fn grin_tx_file_finalize(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    slate_file_path: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password)
+            .and_then(|session| {
+                tx_file_finalize(&session, &cstr_to_str(slate_file_path, "slate_file_path")?)
+            })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_tx_file_finalize(
+    handle: u64,
+    slate_file_path: *const c_char,
     error: *mut u8,
 ) -> *const c_char {
-    let res = listen(
-        &cstr_to_str(json_cfg),
-    );
+    let res = catch_panic(|| {
+        with_session(handle, None, |session| {
+            tx_file_finalize(session, &cstr_to_str(slate_file_path, "slate_file_path")?)
+        })
+    });
     unsafe { result_to_cstr(res, error) }
 }
 
-fn send_tx_by_http(
-    json_cfg: &str,
-    amount: u64,
-    receiver_wallet_url: &str,
-    selection_strategy: &str,
-    target_slate_version: Option<u16>,
-    message: &str,
-) -> Result<String, Error> {
-    let wallet = get_wallet_instance(MobileWalletCfg::from_str(json_cfg)?)?;
-    let api = Owner::new(wallet);
-    let args = InitTxArgs {
-        src_acct_name: None,
-        amount,
-        minimum_confirmations: MINIMUM_CONFIRMATIONS,
-        max_outputs: 500,
-        num_change_outputs: 1,
-        selection_strategy: selection_strategy.to_string(),
-        message: Some(message.to_string()),
-        target_slate_version,
-        estimate_only: None,
-        send_args: None,
-    };
-    let slate_r1 = api.init_send_tx(args)?;
-
-    let adapter = HTTPWalletCommAdapter::new();
-    match adapter.send_tx_sync(receiver_wallet_url, &slate_r1) {
-        Ok(slate) => {
-            api.verify_slate_messages(&slate)?;
-            api.tx_lock_outputs(&slate_r1, 0)?;
-
-            let finalized_slate = api.finalize_tx(&slate);
-            if finalized_slate.is_err() {
-                api.cancel_tx(None, Some(slate_r1.id))?;
-            }
-            let finalized_slate = finalized_slate?;
+fn chain_height(session: &WalletSession) -> Result<String, Error> {
+    let api = Owner::new(session.wallet.clone());
+    let height = api.node_height()?;
+    Ok(serde_json::to_string(&height).unwrap())
+}
 
-            let res = api.post_tx(&finalized_slate.tx, false);
-            if res.is_err() {
-                api.cancel_tx(None, Some(slate_r1.id))?;
-                res?;
-            }
+#[no_mangle]
+pub extern "C" fn grin_chain_height(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password)
+            .and_then(|session| chain_height(&session))
+    });
+    unsafe { result_to_cstr(res, error) }
+}
 
-            Ok(serde_json::to_string(&finalized_slate).expect("fail to serialize slate to json string"))
-        }
-        Err(e) => {
-            Err(Error::from(e))
-        }
-    }
+#[no_mangle]
+pub extern "C" fn grin_session_chain_height(
+    handle: u64,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| with_session_read(handle, None, chain_height));
+    unsafe { result_to_cstr(res, error) }
 }
 
-fn send_tx_by_relay(
-    json_cfg: &str,
+fn invoice_issue(
+    session: &WalletSession,
     amount: u64,
-    receiver_addr: &str,
-    selection_strategy: &str,
     target_slate_version: Option<u16>,
-    message: &str,
+    message: Option<String>,
+    payer_addr: Option<&str>,
 ) -> Result<String, Error> {
-    let config = MobileWalletCfg::from_str(json_cfg)?;
-    let wallet = get_wallet_instance(config.clone())?;
-    let api = Owner::new(wallet.clone());
-    let args = InitTxArgs {
-        src_acct_name: None,
+    let api = Owner::new(session.wallet.clone());
+    let args = IssueInvoiceTxArgs {
+        dest_acct_name: None,
         amount,
-        minimum_confirmations: MINIMUM_CONFIRMATIONS,
-        max_outputs: 500,
-        num_change_outputs: 1,
-        selection_strategy: selection_strategy.to_string(),
-        message: Some(message.to_string()),
+        message,
         target_slate_version,
-        estimate_only: None,
-        send_args: None,
     };
-    let slate_r1 = api.init_send_tx(args)?;
-
-    // The streaming channel between 'grinrelay_listener' and 'GrinrelayWalletCommAdapter'
-    let (relay_tx_as_payer, relay_rx) = channel();
-
-    // Start a Grin Relay service firstly
-    let grinrelay_listener = grinrelay_listener(
-        wallet.clone(),
-        config.grinrelay_config.clone().unwrap_or_default(),
-        Some(relay_tx_as_payer),
-        None,
-    )?;
-    thread::sleep(Duration::from_millis(1_000));
-
-    let adapter = GrinrelayWalletCommAdapter::new(grinrelay_listener, relay_rx);
-    match adapter.send_tx_sync(receiver_addr, &slate_r1.clone()) {
-        Ok(mut slate) => {
-            api.verify_slate_messages(&slate)?;
-            api.tx_lock_outputs(&slate_r1, 0)?;
-
-            let finalized_slate = api.finalize_tx(&mut slate);
-            if finalized_slate.is_err() {
-                api.cancel_tx(None, Some(slate_r1.id))?;
-            }
-            let finalized_slate = finalized_slate?;
-
-            let res = api.post_tx(&finalized_slate.tx, false);
-            if res.is_err() {
-                api.cancel_tx(None, Some(slate_r1.id))?;
-                res?;
-            }
-
-            Ok(serde_json::to_string(&finalized_slate).expect("fail to serialize slate to json string"))
-        }
-        Err(e) => {
-            Err(Error::from(e))
+    let slate = api.issue_invoice_tx(args)?;
+
+    // The listener publishes the invoice, then finalizes and posts the payer's response
+    // in background, whenever it arrives.
+    if let Some(addr) = payer_addr {
+        let config = &session.config;
+        let res = add_issued_invoice(
+            config,
+            IssuedInvoice {
+                slate_id: slate.id.to_string(),
+                addr: addr.to_owned(),
+                amount,
+                created_at: unix_timestamp(),
+            },
+        )
+        .and_then(|_| relay_publish(session, addr, slate.clone()));
+        if let Err(e) = res {
+            let _ = take_issued_invoice(config, &slate.id.to_string());
+            api.cancel_tx(None, Some(slate.id))?;
+            return Err(e);
         }
     }
+    Ok(serde_json::to_string(&slate).expect("fail to serialize slate to json string"))
 }
 
 #[no_mangle]
-pub extern "C" fn grin_send_tx(
+pub extern "C" fn grin_invoice_issue(
     json_cfg: *const c_char,
+    password: *const c_char,
     amount: u64,
-    receiver_addr_or_url: *const c_char,
-    selection_strategy: *const c_char,
     target_slate_version: i16,
     message: *const c_char,
+    payer_addr: *const c_char,
     error: *mut u8,
 ) -> *const c_char {
     let mut slate_version: Option<u16> = None;
@@ -676,155 +2625,496 @@ pub extern "C" fn grin_send_tx(
         slate_version = Some(target_slate_version as u16);
     }
 
-    let receiver = &cstr_to_str(receiver_addr_or_url);
-    let res = if receiver.starts_with("http://") || receiver.starts_with("https://") {
-        send_tx_by_http(
-            &cstr_to_str(json_cfg),
-            amount,
-            receiver,
-            &cstr_to_str(selection_strategy),
-            slate_version,
-            &cstr_to_str(message),
-        )
-    } else {
-        send_tx_by_relay(
-            &cstr_to_str(json_cfg),
-            amount,
-            receiver,
-            &cstr_to_str(selection_strategy),
-            slate_version,
-            &cstr_to_str(message),
-        )
-    };
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password).and_then(|session| {
+            invoice_issue(
+                &session,
+                amount,
+                slate_version,
+                cstr_to_opt_str(message)?,
+                cstr_to_opt_str(payer_addr)?.as_ref().map(|a| a.as_str()),
+            )
+        })
+    });
     unsafe { result_to_cstr(res, error) }
 }
 
-fn cancel_tx(json_cfg: &str, tx_slate_id: &str) -> Result<String, Error> {
-    let uuid = Uuid::parse_str(tx_slate_id).map_err(|e| ErrorKind::GenericError(e.to_string()))?;
-    let wallet = get_wallet_instance(MobileWalletCfg::from_str(json_cfg)?)?;
-    let api = Owner::new(wallet);
-    api.cancel_tx(None, Some(uuid))?;
-    Ok("OK".to_owned())
-}
-
 #[no_mangle]
-pub extern "C" fn grin_cancel_tx(
-    json_cfg: *const c_char,
-    tx_slate_id: *const c_char,
+pub extern "C" fn grin_session_invoice_issue(
+    handle: u64,
+    account: *const c_char,
+    amount: u64,
+    target_slate_version: i16,
+    message: *const c_char,
+    payer_addr: *const c_char,
     error: *mut u8,
 ) -> *const c_char {
-    let res = cancel_tx(&cstr_to_str(json_cfg), &cstr_to_str(tx_slate_id));
+    let mut slate_version: Option<u16> = None;
+    if target_slate_version >= 0 {
+        slate_version = Some(target_slate_version as u16);
+    }
+
+    let res = catch_panic(|| {
+        let account = cstr_to_opt_str(account)?;
+        with_session(handle, account.as_ref().map(|a| a.as_str()), |session| {
+            invoice_issue(
+                session,
+                amount,
+                slate_version,
+                cstr_to_opt_str(message)?,
+                cstr_to_opt_str(payer_addr)?.as_ref().map(|a| a.as_str()),
+            )
+        })
+    });
     unsafe { result_to_cstr(res, error) }
 }
 
-fn post_tx(json_cfg: &str, tx_slate_id: &str) -> Result<String, Error> {
-    let wallet = get_wallet_instance(MobileWalletCfg::from_str(json_cfg)?)?;
-    let api = Owner::new(wallet);
-    let uuid = Uuid::parse_str(tx_slate_id).map_err(|e| ErrorKind::GenericError(e.to_string()))?;
-    let (validated, txs) = api.retrieve_txs(true, None, Some(uuid))?;
-    if txs[0].confirmed {
-        return Err(Error::from(ErrorKind::GenericError(format!(
-            "Transaction already confirmed"
-        ))));
-    } else if !validated {
-        return Err(Error::from(ErrorKind::GenericError(format!(
-            "api.retrieve_txs not validated"
+/// Pay an invoice, if its amount and fee are approved by the payer.
+/// The outputs are locked only once the fee is known to be approved.
+fn process_invoice(
+    session: &WalletSession,
+    slate: &Slate,
+    approved_amount: u64,
+    max_fee: u64,
+    selection_strategy: &str,
+    message: Option<String>,
+    options: &SendOptions,
+) -> Result<Slate, Error> {
+    if !is_invoice(slate) {
+        return Err(Error::from(ErrorKind::ArgumentError(format!(
+            "slate {} is not an invoice",
+            slate.id
         ))));
     }
+    if slate.amount != approved_amount {
+        return Err(LibError::InvoiceNotApproved.into_error(format!(
+            "invoice amount {} differs from the approved amount {}",
+            slate.amount, approved_amount
+        )));
+    }
 
-    let stored_tx = api.get_stored_tx(&txs[0])?;
-    match stored_tx {
-        Some(stored_tx) => {
-            api.post_tx(&stored_tx, false)?;
-            Ok("OK".to_owned())
+    let api = Owner::new(session.wallet.clone());
+    api.verify_slate_messages(slate)?;
+    let args = options.init_tx_args(
+        &session.config,
+        slate.amount,
+        selection_strategy,
+        None,
+        message,
+    );
+    let slate = options.with_coin_control(session, || {
+        let slate = api.process_invoice_tx(slate, args)?;
+        if slate.fee > max_fee {
+            return Err(LibError::InvoiceNotApproved.into_error(format!(
+                "invoice fee {} exceeds the approved fee {}",
+                slate.fee, max_fee
+            )));
         }
-        None => Err(Error::from(ErrorKind::GenericError(format!(
-            "transaction data not found"
-        )))),
-    }
+        api.tx_lock_outputs(&slate, 0)?;
+        Ok(slate)
+    })?;
+    Ok(slate)
+}
+
+fn invoice_process(
+    session: &WalletSession,
+    slate_json: &str,
+    approved_amount: u64,
+    max_fee: u64,
+    selection_strategy: &str,
+    message: Option<String>,
+    options: &SendOptions,
+) -> Result<String, Error> {
+    let slate = Slate::deserialize_upgrade(slate_json)?;
+    let slate = process_invoice(
+        session,
+        &slate,
+        approved_amount,
+        max_fee,
+        selection_strategy,
+        message,
+        options,
+    )?;
+    Ok(serde_json::to_string(&slate).expect("fail to serialize slate to json string"))
 }
 
 #[no_mangle]
-pub extern "C" fn grin_post_tx(
+pub extern "C" fn grin_invoice_process(
     json_cfg: *const c_char,
-    tx_slate_id: *const c_char,
+    password: *const c_char,
+    slate_json: *const c_char,
+    approved_amount: u64,
+    max_fee: u64,
+    selection_strategy: *const c_char,
+    message: *const c_char,
+    send_options: *const c_char,
     error: *mut u8,
 ) -> *const c_char {
-    let res = post_tx(
-        &cstr_to_str(json_cfg),
-        &cstr_to_str(tx_slate_id),
-    );
+    let res = catch_panic(|| {
+        let options = SendOptions::from_str(cstr_to_opt_str(send_options)?)?;
+        session_from_json(json_cfg, password).and_then(|session| {
+            invoice_process(
+                &session,
+                &cstr_to_str(slate_json, "slate_json")?,
+                approved_amount,
+                max_fee,
+                &cstr_to_str(selection_strategy, "selection_strategy")?,
+                cstr_to_opt_str(message)?,
+                &options,
+            )
+        })
+    });
     unsafe { result_to_cstr(res, error) }
 }
 
-fn tx_file_receive(
-    json_cfg: &str,
+#[no_mangle]
+pub extern "C" fn grin_session_invoice_process(
+    handle: u64,
+    account: *const c_char,
+    slate_json: *const c_char,
+    approved_amount: u64,
+    max_fee: u64,
+    selection_strategy: *const c_char,
+    message: *const c_char,
+    send_options: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        let options = SendOptions::from_str(cstr_to_opt_str(send_options)?)?;
+        let account = cstr_to_opt_str(account)?;
+        with_session(handle, account.as_ref().map(|a| a.as_str()), |session| {
+            invoice_process(
+                session,
+                &cstr_to_str(slate_json, "slate_json")?,
+                approved_amount,
+                max_fee,
+                &cstr_to_str(selection_strategy, "selection_strategy")?,
+                cstr_to_opt_str(message)?,
+                &options,
+            )
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+fn invoice_file_process(
+    session: &WalletSession,
     slate_file_path: &str,
-    message: &str,
+    response_file_path: &str,
+    approved_amount: u64,
+    max_fee: u64,
+    selection_strategy: &str,
+    message: Option<String>,
+    options: &SendOptions,
 ) -> Result<String, Error> {
-    let config = MobileWalletCfg::from_str(json_cfg)?;
-    let wallet = get_wallet_instance(config.clone())?;
-    let api = Foreign::new(wallet, None);
     let adapter = FileWalletCommAdapter::new();
-    let mut slate = adapter.receive_tx_async(&slate_file_path)?;
-    api.verify_slate_messages(&slate)?;
-    slate = api.receive_tx(&slate, Some(&config.account), Some(message.to_string()))?;
+    let slate = adapter.receive_tx_async(slate_file_path)?;
+    let slate = process_invoice(
+        session,
+        &slate,
+        approved_amount,
+        max_fee,
+        selection_strategy,
+        message,
+        options,
+    )?;
+    if let Err(e) = adapter.send_tx_async(response_file_path, &slate) {
+        let api = Owner::new(session.wallet.clone());
+        api.cancel_tx(None, Some(slate.id))?;
+        return Err(e);
+    }
     Ok(serde_json::to_string(&slate).expect("fail to serialize slate to json string"))
 }
 
 #[no_mangle]
-pub extern "C" fn grin_tx_file_receive(
+pub extern "C" fn grin_invoice_file_process(
     json_cfg: *const c_char,
+    password: *const c_char,
     slate_file_path: *const c_char,
+    response_file_path: *const c_char,
+    approved_amount: u64,
+    max_fee: u64,
+    selection_strategy: *const c_char,
     message: *const c_char,
+    send_options: *const c_char,
     error: *mut u8,
 ) -> *const c_char {
-    let res = tx_file_receive(
-        &cstr_to_str(json_cfg),
-        &cstr_to_str(slate_file_path),
-        &cstr_to_str(message),
-    );
+    let res = catch_panic(|| {
+        let options = SendOptions::from_str(cstr_to_opt_str(send_options)?)?;
+        session_from_json(json_cfg, password).and_then(|session| {
+            invoice_file_process(
+                &session,
+                &cstr_to_str(slate_file_path, "slate_file_path")?,
+                &cstr_to_str(response_file_path, "response_file_path")?,
+                approved_amount,
+                max_fee,
+                &cstr_to_str(selection_strategy, "selection_strategy")?,
+                cstr_to_opt_str(message)?,
+                &options,
+            )
+        })
+    });
     unsafe { result_to_cstr(res, error) }
 }
 
-fn tx_file_finalize(
-    json_cfg: &str,
-    slate_file_path: &str,
+#[no_mangle]
+pub extern "C" fn grin_session_invoice_file_process(
+    handle: u64,
+    account: *const c_char,
+    slate_file_path: *const c_char,
+    response_file_path: *const c_char,
+    approved_amount: u64,
+    max_fee: u64,
+    selection_strategy: *const c_char,
+    message: *const c_char,
+    send_options: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        let options = SendOptions::from_str(cstr_to_opt_str(send_options)?)?;
+        let account = cstr_to_opt_str(account)?;
+        with_session(handle, account.as_ref().map(|a| a.as_str()), |session| {
+            invoice_file_process(
+                session,
+                &cstr_to_str(slate_file_path, "slate_file_path")?,
+                &cstr_to_str(response_file_path, "response_file_path")?,
+                approved_amount,
+                max_fee,
+                &cstr_to_str(selection_strategy, "selection_strategy")?,
+                cstr_to_opt_str(message)?,
+                &options,
+            )
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+fn invoices_received(session: &WalletSession) -> Result<String, Error> {
+    let invoices = received_invoices(&session.config)?;
+    Ok(serde_json::to_string(&invoices).unwrap())
+}
+
+#[no_mangle]
+pub extern "C" fn grin_invoices_received(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password)
+            .and_then(|session| invoices_received(&session))
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_invoices_received(
+    handle: u64,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| with_session_read(handle, None, invoices_received));
+    unsafe { result_to_cstr(res, error) }
+}
+
+/// Pay an invoice received by the listener, and send the response back to the payee.
+fn invoice_pay(
+    session: &WalletSession,
+    tx_slate_id: &str,
+    approved_amount: u64,
+    max_fee: u64,
+    selection_strategy: &str,
+    message: Option<String>,
+    options: &SendOptions,
 ) -> Result<String, Error> {
-    let wallet = get_wallet_instance(MobileWalletCfg::from_str(json_cfg)?)?;
-    let api = Owner::new(wallet);
-    let adapter = FileWalletCommAdapter::new();
-    let mut slate = adapter.receive_tx_async(slate_file_path)?;
-    api.verify_slate_messages(&slate)?;
-    slate = api.finalize_tx(&slate)?;
+    let config = &session.config;
+    let invoice = take_received_invoice(config, tx_slate_id)?.ok_or_else(|| {
+        Error::from(ErrorKind::ArgumentError(format!(
+            "unknown received invoice: {}",
+            tx_slate_id
+        )))
+    })?;
+
+    let res = process_invoice(
+        session,
+        &invoice.slate,
+        approved_amount,
+        max_fee,
+        selection_strategy,
+        message,
+        options,
+    )
+    .and_then(|slate| {
+        let res = relay_publish(session, &invoice.addr, slate.clone());
+        if res.is_err() {
+            Owner::new(session.wallet.clone()).cancel_tx(None, Some(slate.id))?;
+        }
+        res
+    });
+    // Keep the invoice to be paid later
+    if let Err(e) = res {
+        add_received_invoice(config, invoice)?;
+        return Err(e);
+    }
+    Ok(serde_json::to_string(tx_slate_id).unwrap())
+}
+
+#[no_mangle]
+pub extern "C" fn grin_invoice_pay(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    tx_slate_id: *const c_char,
+    approved_amount: u64,
+    max_fee: u64,
+    selection_strategy: *const c_char,
+    message: *const c_char,
+    send_options: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        let options = SendOptions::from_str(cstr_to_opt_str(send_options)?)?;
+        session_from_json(json_cfg, password).and_then(|session| {
+            invoice_pay(
+                &session,
+                &cstr_to_str(tx_slate_id, "tx_slate_id")?,
+                approved_amount,
+                max_fee,
+                &cstr_to_str(selection_strategy, "selection_strategy")?,
+                cstr_to_opt_str(message)?,
+                &options,
+            )
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_invoice_pay(
+    handle: u64,
+    account: *const c_char,
+    tx_slate_id: *const c_char,
+    approved_amount: u64,
+    max_fee: u64,
+    selection_strategy: *const c_char,
+    message: *const c_char,
+    send_options: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        let options = SendOptions::from_str(cstr_to_opt_str(send_options)?)?;
+        let account = cstr_to_opt_str(account)?;
+        with_session(handle, account.as_ref().map(|a| a.as_str()), |session| {
+            invoice_pay(
+                session,
+                &cstr_to_str(tx_slate_id, "tx_slate_id")?,
+                approved_amount,
+                max_fee,
+                &cstr_to_str(selection_strategy, "selection_strategy")?,
+                cstr_to_opt_str(message)?,
+                &options,
+            )
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+fn invoice_reject(session: &WalletSession, tx_slate_id: &str) -> Result<String, Error> {
+    take_received_invoice(&session.config, tx_slate_id)?.ok_or_else(|| {
+        Error::from(ErrorKind::ArgumentError(format!(
+            "unknown received invoice: {}",
+            tx_slate_id
+        )))
+    })?;
+    Ok("OK".to_owned())
+}
+
+#[no_mangle]
+pub extern "C" fn grin_invoice_reject(
+    json_cfg: *const c_char,
+    password: *const c_char,
+    tx_slate_id: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        session_from_json(json_cfg, password).and_then(|session| {
+            invoice_reject(&session, &cstr_to_str(tx_slate_id, "tx_slate_id")?)
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_invoice_reject(
+    handle: u64,
+    tx_slate_id: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        with_session(handle, None, |session| {
+            invoice_reject(session, &cstr_to_str(tx_slate_id, "tx_slate_id")?)
+        })
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+fn invoice_finalize(session: &WalletSession, slate: &Slate) -> Result<String, Error> {
+    let slate = finalize_invoice(session.wallet.clone(), slate)?;
     Ok(serde_json::to_string(&slate).expect("fail to serialize slate to json string"))
 }
 
 #[no_mangle]
-pub extern "C" fn grin_tx_file_finalize(
+pub extern "C" fn grin_invoice_finalize(
     json_cfg: *const c_char,
-    slate_file_path: *const c_char,
+    password: *const c_char,
+    slate_json: *const c_char,
     error: *mut u8,
 ) -> *const c_char {
-    let res = tx_file_finalize(
-        &cstr_to_str(json_cfg),
-        &cstr_to_str(slate_file_path),
-    );
+    let res = catch_panic(|| {
+        let slate = Slate::deserialize_upgrade(&cstr_to_str(slate_json, "slate_json")?)?;
+        session_from_json(json_cfg, password)
+            .and_then(|session| invoice_finalize(&session, &slate))
+    });
     unsafe { result_to_cstr(res, error) }
 }
 
-fn chain_height(json_cfg: &str) -> Result<String, Error> {
-    let wallet = get_wallet_instance(MobileWalletCfg::from_str(json_cfg)?)?;
-    let api = Owner::new(wallet);
-    let height = api.node_height()?;
-    Ok(serde_json::to_string(&height).unwrap())
+#[no_mangle]
+pub extern "C" fn grin_session_invoice_finalize(
+    handle: u64,
+    slate_json: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        let slate = Slate::deserialize_upgrade(&cstr_to_str(slate_json, "slate_json")?)?;
+        with_session(handle, None, |session| invoice_finalize(session, &slate))
+    });
+    unsafe { result_to_cstr(res, error) }
 }
 
 #[no_mangle]
-pub extern "C" fn grin_chain_height(
+pub extern "C" fn grin_invoice_file_finalize(
     json_cfg: *const c_char,
+    password: *const c_char,
+    slate_file_path: *const c_char,
+    error: *mut u8,
+) -> *const c_char {
+    let res = catch_panic(|| {
+        let adapter = FileWalletCommAdapter::new();
+        let slate = adapter.receive_tx_async(&cstr_to_str(slate_file_path, "slate_file_path")?)?;
+        session_from_json(json_cfg, password)
+            .and_then(|session| invoice_finalize(&session, &slate))
+    });
+    unsafe { result_to_cstr(res, error) }
+}
+
+#[no_mangle]
+pub extern "C" fn grin_session_invoice_file_finalize(
+    handle: u64,
+    slate_file_path: *const c_char,
     error: *mut u8,
 ) -> *const c_char {
-    let res = chain_height(&cstr_to_str(json_cfg));
+    let res = catch_panic(|| {
+        let adapter = FileWalletCommAdapter::new();
+        let slate = adapter.receive_tx_async(&cstr_to_str(slate_file_path, "slate_file_path")?)?;
+        with_session(handle, None, |session| invoice_finalize(session, &slate))
+    });
     unsafe { result_to_cstr(res, error) }
 }
@@ -0,0 +1,601 @@
+// Copyright 2019 Gary Yu.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Grin Relay listeners, which can be started, stopped and queried by the app.
+
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Sender, TryRecvError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use grin_wallet_api::{Foreign, Owner};
+use grin_wallet_controller::grinrelay_listener;
+use grin_wallet_impls::{Error, ErrorKind};
+use grin_wallet_libwallet::{Slate, SlateVersion, VersionedSlate};
+use grin_wallet_util::grin_util::Mutex;
+
+use crate::error::LibError;
+use crate::events::{emit, unix_timestamp, Event, EventType};
+use crate::invoice::{
+    add_received_invoice, finalize_invoice_response, is_invoice, issued_invoice,
+    take_issued_invoice, ReceivedInvoice,
+};
+use crate::pending::{pending_send, take_pending_send};
+use crate::proof::{add_payment_proof, save_payment_proof};
+use crate::session::{WalletInstance, WalletSession};
+use crate::{panic_message, MobileWalletCfg};
+
+/// Connection state of a listener
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ListenerState {
+    Connecting,
+    Connected,
+    Stopped,
+}
+
+/// Listener status, as reported to the app
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ListenerStatus {
+    pub id: u64,
+    pub state: ListenerState,
+    /// Connected, and the relay address is registered, so that slates can be sent.
+    pub ready: bool,
+    pub relay_addr: Option<String>,
+    pub last_error: Option<String>,
+    pub slates_received: u64,
+}
+
+struct Listener {
+    /// Id of the session the listener was started for
+    session_id: u64,
+    stop: Arc<AtomicBool>,
+    status: Arc<Mutex<ListenerStatus>>,
+    /// The slates to be published by the listener, with the receiver relay address
+    /// and the sender of the publish result
+    outbox: Sender<(String, Slate, Sender<Result<(), String>>)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Listener {
+    fn is_running(&self) -> bool {
+        self.status.lock().state != ListenerState::Stopped
+    }
+}
+
+struct Registry {
+    next_id: u64,
+    listeners: HashMap<u64, Listener>,
+}
+
+lazy_static! {
+    static ref LISTENERS: Mutex<Registry> = Mutex::new(Registry {
+        next_id: 1,
+        listeners: HashMap::new(),
+    });
+}
+
+/// Start a Grin Relay listener for the wallet and return its id.
+///
+/// Only one listener runs for a session: if it's already listening,
+/// the id of the running listener is returned. The listener of another session, even
+/// of the same data directory, has none of the private contexts of this session's
+/// transactions, see `PassphraseBackend`.
+///
+/// The listener receives the slates sent to the wallet and sends them back signed,
+/// keeps the received invoices for the user's approval, and finalizes and posts
+/// the responses of the pending relay sends and of the issued invoices.
+pub fn start_listener(session: &WalletSession) -> Result<u64, Error> {
+    let mut registry = LISTENERS.lock();
+    let running = registry
+        .listeners
+        .iter()
+        .find(|(_, l)| l.session_id == session.id() && l.is_running());
+    if let Some((id, _)) = running {
+        return Ok(*id);
+    }
+
+    let id = registry.next_id;
+    let listener = spawn_listener(id, session)?;
+    registry.next_id += 1;
+    registry.listeners.insert(id, listener);
+    Ok(id)
+}
+
+/// Start the listeners of a session again with the same ids, for a session re-opened
+/// after their previous session was closed by `stop_listeners`.
+pub fn restart_listeners(ids: &[u64], session: &WalletSession) -> Result<(), Error> {
+    let mut registry = LISTENERS.lock();
+    for &id in ids {
+        let listener = spawn_listener(id, session)?;
+        registry.listeners.insert(id, listener);
+    }
+    Ok(())
+}
+
+fn spawn_listener(id: u64, session: &WalletSession) -> Result<Listener, Error> {
+    let config = session.config.clone();
+    let wallet = session.wallet.clone();
+    let account_lock = session.account_lock();
+
+    // The streaming channels between 'grinrelay_listener' and 'foreign_listener'
+    let (relay_tx_as_payer, payer_rx) = channel();
+    let (relay_tx_as_payee, payee_rx) = channel();
+    let (outbox, outbox_rx) = channel::<(String, Slate, Sender<Result<(), String>>)>();
+
+    // Start a Grin Relay service firstly
+    let grinrelay_listener = grinrelay_listener(
+        wallet.clone(),
+        config.grinrelay_config.clone().unwrap_or_default(),
+        Some(relay_tx_as_payer),
+        Some(relay_tx_as_payee),
+    )?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let status = Arc::new(Mutex::new(ListenerStatus {
+        id,
+        state: ListenerState::Connecting,
+        ready: false,
+        relay_addr: None,
+        last_error: None,
+        slates_received: 0,
+    }));
+
+    let thread_stop = stop.clone();
+    let thread_status = status.clone();
+    let handle = thread::Builder::new()
+        .name(format!("foreign-listener-{}", id))
+        .spawn(move || {
+            let api = Foreign::new(wallet.clone(), None);
+            // A panic stops the listener, with its status telling it, instead of leaving
+            // it connected but deaf
+            let res = panic::catch_unwind(AssertUnwindSafe(|| {
+                while !thread_stop.load(Ordering::Relaxed) {
+                    {
+                        let mut status = thread_status.lock();
+                        if grinrelay_listener.is_connected() {
+                            status.state = ListenerState::Connected;
+                            if status.relay_addr.is_none() {
+                                status.relay_addr = Some(grinrelay_listener.get_relay_addr());
+                            }
+                        } else {
+                            status.state = ListenerState::Connecting;
+                        }
+                        status.ready =
+                            status.state == ListenerState::Connected && status.relay_addr.is_some();
+                    }
+
+                    // Publish the slates of our relay sends
+                    while let Ok((addr, slate, result)) = outbox_rx.try_recv() {
+                        let versioned_slate =
+                            VersionedSlate::into_version(slate.clone(), SlateVersion::V2);
+                        match grinrelay_listener.publish(&versioned_slate, &addr) {
+                            Ok(_) => {
+                                emit(Event::new(EventType::SlateSent, id, &addr, &slate));
+                                let _ = result.send(Ok(()));
+                            }
+                            Err(e) => {
+                                let error = format!("{}", e);
+                                thread_status.lock().last_error = Some(format!(
+                                    "slate {} fail to send to {}: {}",
+                                    slate.id, addr, error
+                                ));
+                                emit(
+                                    Event::new(EventType::SendFailed, id, &addr, &slate)
+                                        .with_error(error.clone()),
+                                );
+                                let _ = result.send(Err(error));
+                            }
+                        }
+                    }
+
+                    // The slates are only processed while the session account is active, not
+                    // during a call on another account. The publishing above isn't, since such
+                    // a call may wait for it.
+                    let account_guard = match account_lock.try_read() {
+                        Some(guard) => guard,
+                        None => {
+                            thread::sleep(Duration::from_millis(100));
+                            continue;
+                        }
+                    };
+
+                    // The responses of our relay sends
+                    match payer_rx.try_recv() {
+                        Ok((addr, slate)) => {
+                            thread_status.lock().slates_received += 1;
+                            catch_slate_panic(&thread_status, &addr, || {
+                                process_response(
+                                    id,
+                                    &config,
+                                    wallet.clone(),
+                                    &addr,
+                                    slate,
+                                    &thread_status,
+                                )
+                            });
+                        }
+                        Err(TryRecvError::Disconnected) => {
+                            thread_status.lock().last_error =
+                                Some("grin relay listener disconnected".to_owned());
+                            break;
+                        }
+                        Err(TryRecvError::Empty) => {}
+                    }
+
+                    // The slates sent to us
+                    match payee_rx.try_recv() {
+                        Ok((addr, slate)) => {
+                            thread_status.lock().slates_received += 1;
+
+                            // A response signed by both parties, i.e. of our issued invoice
+                            if slate.participant_data.len() > 1 {
+                                catch_slate_panic(&thread_status, &addr, || {
+                                    process_response(
+                                        id,
+                                        &config,
+                                        wallet.clone(),
+                                        &addr,
+                                        slate,
+                                        &thread_status,
+                                    )
+                                });
+                                continue;
+                            }
+
+                            emit(Event::new(EventType::SlateReceived, id, &addr, &slate));
+
+                            if let Err(e) = api.verify_slate_messages(&slate) {
+                                let error = format!("{}", e);
+                                thread_status.lock().last_error = Some(format!(
+                                    "slate {} from {} fail to verify: {}",
+                                    slate.id, addr, error
+                                ));
+                                emit(
+                                    Event::new(EventType::VerificationFailed, id, &addr, &slate)
+                                        .with_error(error),
+                                );
+                                continue;
+                            }
+
+                            // An invoice is only paid after the user approves it
+                            if is_invoice(&slate) {
+                                let invoice = ReceivedInvoice {
+                                    addr: addr.clone(),
+                                    slate: slate.clone(),
+                                    received_at: unix_timestamp(),
+                                };
+                                match add_received_invoice(&config, invoice) {
+                                    Ok(_) => emit(Event::new(
+                                        EventType::InvoiceReceived,
+                                        id,
+                                        &addr,
+                                        &slate,
+                                    )),
+                                    Err(e) => {
+                                        thread_status.lock().last_error = Some(format!(
+                                            "invoice {} from {} fail to save: {}",
+                                            slate.id, addr, e
+                                        ));
+                                    }
+                                }
+                                continue;
+                            }
+
+                            let res = api
+                                .receive_tx(&slate, Some(&config.account), None)
+                                .map_err(|e| format!("{}", e))
+                                .and_then(|mut slate_rx| {
+                                    // The payment goes on without a payment proof
+                                    let sender = Some(addr.as_str());
+                                    let receiver = grinrelay_listener.get_relay_addr();
+                                    let res = panic::catch_unwind(AssertUnwindSafe(|| {
+                                        add_payment_proof(
+                                            &wallet,
+                                            &mut slate_rx,
+                                            sender,
+                                            Some(receiver.as_str()),
+                                        )
+                                    }))
+                                    .unwrap_or_else(|payload| {
+                                        Err(Error::from(ErrorKind::GenericError(panic_message(
+                                            payload.as_ref(),
+                                        ))))
+                                    });
+                                    if let Err(e) = res {
+                                        thread_status.lock().last_error = Some(format!(
+                                            "slate {} from {} fail to sign payment proof: {}",
+                                            slate.id, addr, e
+                                        ));
+                                    }
+                                    let versioned_slate =
+                                        VersionedSlate::into_version(slate_rx, SlateVersion::V2);
+                                    grinrelay_listener
+                                        .publish(&versioned_slate, &addr.to_owned())
+                                        .map_err(|e| format!("{}", e))
+                                });
+                            match res {
+                                Ok(_) => {
+                                    emit(Event::new(EventType::SlateResponded, id, &addr, &slate));
+                                }
+                                Err(error) => {
+                                    thread_status.lock().last_error = Some(format!(
+                                        "slate {} from {} fail to respond: {}",
+                                        slate.id, addr, error
+                                    ));
+                                    emit(
+                                        Event::new(EventType::RespondFailed, id, &addr, &slate)
+                                            .with_error(error),
+                                    );
+                                }
+                            }
+                        }
+                        Err(TryRecvError::Disconnected) => {
+                            thread_status.lock().last_error =
+                                Some("grin relay listener disconnected".to_owned());
+                            break;
+                        }
+                        Err(TryRecvError::Empty) => {}
+                    }
+                    drop(account_guard);
+                    thread::sleep(Duration::from_millis(100));
+                }
+            }));
+            let mut status = thread_status.lock();
+            if let Err(payload) = res {
+                status.last_error = Some(format!(
+                    "listener panicked: {}",
+                    panic_message(payload.as_ref())
+                ));
+            }
+            status.state = ListenerState::Stopped;
+            status.ready = false;
+        })
+        .map_err(|e| {
+            Error::from(ErrorKind::GenericError(format!(
+                "listen thread fail to start: {}",
+                e
+            )))
+        })?;
+
+    Ok(Listener {
+        session_id: session.id(),
+        stop,
+        status,
+        outbox,
+        thread: Some(handle),
+    })
+}
+
+/// Publish a slate by a listener to a relay address, and wait for the result.
+pub fn publish_slate(id: u64, addr: &str, slate: Slate) -> Result<(), Error> {
+    let outbox = LISTENERS
+        .lock()
+        .listeners
+        .get(&id)
+        .filter(|l| l.is_running())
+        .map(|l| l.outbox.clone())
+        .ok_or_else(|| {
+            Error::from(ErrorKind::GenericError(format!(
+                "listener {} is not running",
+                id
+            )))
+        })?;
+    let (result, result_rx) = channel();
+    outbox
+        .send((addr.to_owned(), slate, result))
+        .map_err(|e| Error::from(ErrorKind::GenericError(e.to_string())))?;
+    // The result sender is dropped without a result if the listener stops before publishing
+    match result_rx.recv() {
+        Ok(res) => res.map_err(|e| {
+            Error::from(ErrorKind::GenericError(format!(
+                "fail to publish slate to {}: {}",
+                addr, e
+            )))
+        }),
+        Err(_) => Err(Error::from(ErrorKind::GenericError(format!(
+            "listener {} stopped before publishing",
+            id
+        )))),
+    }
+}
+
+/// Wait until a listener is ready to send slates, or fail with `RelayNotConnected`
+/// when the timeout expires.
+pub fn wait_ready(id: u64, timeout: Duration) -> Result<(), Error> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let status = listener_status(id)?;
+        if status.ready {
+            return Ok(());
+        }
+        if status.state == ListenerState::Stopped || Instant::now() >= deadline {
+            let message = match status.last_error {
+                Some(e) => format!("listener {} not ready: {}", id, e),
+                None => format!("listener {} not ready in {:?}", id, timeout),
+            };
+            return Err(LibError::RelayNotConnected.into_error(message));
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// Finalize and post the response of a pending relay send or of an issued invoice.
+///
+/// A response which can't be finalized leaves the transaction as it is, so that the
+/// right response can still be finalized. A finalized transaction which can't be
+/// posted is kept, to be posted again.
+fn process_response(
+    id: u64,
+    config: &MobileWalletCfg,
+    wallet: WalletInstance,
+    addr: &str,
+    slate: Slate,
+    status: &Mutex<ListenerStatus>,
+) {
+    let slate_id = slate.id.to_string();
+    let res = pending_send(config, &slate_id).and_then(|send| match send {
+        Some(send) => Ok(Some((false, send.addr))),
+        None => issued_invoice(config, &slate_id).map(|i| i.map(|i| (true, i.addr))),
+    });
+    let is_invoice = match res {
+        // Only the counterparty the slate was sent to can respond to it
+        Ok(Some((_, expected_addr))) if expected_addr != addr => {
+            status.lock().last_error = Some(format!(
+                "slate {} from {} is expected from {}",
+                slate_id, addr, expected_addr
+            ));
+            return;
+        }
+        Ok(Some((is_invoice, _))) => is_invoice,
+        Ok(None) => {
+            status.lock().last_error = Some(format!(
+                "slate {} from {} is neither a pending send nor an issued invoice",
+                slate_id, addr
+            ));
+            return;
+        }
+        Err(e) => {
+            status.lock().last_error = Some(format!("{}", e));
+            return;
+        }
+    };
+
+    let (res, finalized, failed) = if is_invoice {
+        (
+            finalize_invoice_response(wallet.clone(), &slate),
+            EventType::InvoiceFinalized,
+            EventType::InvoiceFailed,
+        )
+    } else {
+        (
+            finalize_response(wallet.clone(), &slate),
+            EventType::SendFinalized,
+            EventType::SendFailed,
+        )
+    };
+    let finalized_slate = match res {
+        Ok(finalized_slate) => finalized_slate,
+        Err(e) => {
+            let error = format!("{}", e);
+            status.lock().last_error = Some(format!(
+                "slate {} from {} fail to finalize: {}",
+                slate_id, addr, error
+            ));
+            emit(Event::new(failed, id, addr, &slate).with_error(error));
+            return;
+        }
+    };
+
+    // No other response is expected once finalized
+    let res = if is_invoice {
+        take_issued_invoice(config, &slate_id).map(|_| ())
+    } else {
+        take_pending_send(config, &slate_id).map(|_| ())
+    };
+    if let Err(e) = res {
+        status.lock().last_error = Some(format!("{}", e));
+    }
+    if !is_invoice {
+        if let Err(e) = save_payment_proof(config, &finalized_slate, addr) {
+            status.lock().last_error = Some(format!(
+                "slate {} from {} fail to save payment proof: {}",
+                slate_id, addr, e
+            ));
+        }
+    }
+
+    match Owner::new(wallet).post_tx(&finalized_slate.tx, false) {
+        Ok(_) => emit(Event::new(finalized, id, addr, &finalized_slate)),
+        Err(e) => {
+            let error = format!("{}", e);
+            status.lock().last_error = Some(format!(
+                "slate {} from {} fail to post: {}",
+                slate_id, addr, error
+            ));
+            emit(Event::new(failed, id, addr, &finalized_slate).with_error(error));
+        }
+    }
+}
+
+/// Handle a slate, so that a panic only fails this slate instead of the listener
+fn catch_slate_panic<F: FnOnce()>(status: &Mutex<ListenerStatus>, addr: &str, f: F) {
+    if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(f)) {
+        status.lock().last_error = Some(format!(
+            "slate from {} fail to process: {}",
+            addr,
+            panic_message(payload.as_ref())
+        ));
+    }
+}
+
+/// Finalize the payee's response of a relay send, without posting it
+fn finalize_response(wallet: WalletInstance, slate: &Slate) -> Result<Slate, Error> {
+    let api = Owner::new(wallet);
+    api.verify_slate_messages(slate)?;
+    api.finalize_tx(slate)
+}
+
+/// Stop a listener, and wait for its thread to exit.
+pub fn stop_listener(id: u64) -> Result<(), Error> {
+    let handle = {
+        let mut registry = LISTENERS.lock();
+        let listener = registry
+            .listeners
+            .get_mut(&id)
+            .ok_or_else(|| unknown_listener(id))?;
+        listener.stop.store(true, Ordering::Relaxed);
+        listener.thread.take()
+    };
+    if let Some(handle) = handle {
+        let _ = handle.join();
+    }
+    Ok(())
+}
+
+/// Stop the running listeners of a session, which hold its wallet instance, and return
+/// their ids.
+pub fn stop_listeners(session_id: u64) -> Vec<u64> {
+    let ids: Vec<u64> = LISTENERS
+        .lock()
+        .listeners
+        .iter()
+        .filter(|(_, l)| l.session_id == session_id && l.thread.is_some())
+        .map(|(id, _)| *id)
+        .collect();
+    for &id in &ids {
+        let _ = stop_listener(id);
+    }
+    ids
+}
+
+/// Get the status of a listener
+pub fn listener_status(id: u64) -> Result<ListenerStatus, Error> {
+    LISTENERS
+        .lock()
+        .listeners
+        .get(&id)
+        .map(|l| l.status.lock().clone())
+        .ok_or_else(|| unknown_listener(id))
+}
+
+fn unknown_listener(id: u64) -> Error {
+    Error::from(ErrorKind::ArgumentError(format!("unknown listener id: {}", id)))
+}
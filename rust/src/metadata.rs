@@ -0,0 +1,174 @@
+// Copyright 2019 Gary Yu.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Local transaction metadata: a private note, tags and the counterparty,
+//! keyed by the tx slate id. They are never sent to the counterparty.
+
+use std::collections::{BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use grin_wallet_impls::{Error, ErrorKind};
+use grin_wallet_libwallet::TxLogEntry;
+
+use crate::store;
+use crate::MobileWalletCfg;
+
+const TX_METADATA_FILE: &str = "tx_metadata.json";
+
+/// Maximum length of a note, in bytes
+pub const MAX_NOTE_LENGTH: usize = 1000;
+
+/// The metadata of a transaction
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TxMetadata {
+    pub note: Option<String>,
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+    /// The relay address or the url of the counterparty
+    pub counterparty: Option<String>,
+}
+
+impl TxMetadata {
+    fn is_empty(&self) -> bool {
+        self.note.is_none() && self.tags.is_empty() && self.counterparty.is_none()
+    }
+
+    fn matches(&self, text: &str) -> bool {
+        let contains = |s: &String| s.to_lowercase().contains(text);
+        self.note.as_ref().map_or(false, contains)
+            || self.counterparty.as_ref().map_or(false, contains)
+            || self.tags.iter().any(contains)
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        if self
+            .note
+            .as_ref()
+            .map_or(false, |n| n.len() > MAX_NOTE_LENGTH)
+        {
+            return Err(Error::from(ErrorKind::ArgumentError(format!(
+                "note is longer than {} bytes",
+                MAX_NOTE_LENGTH
+            ))));
+        }
+        if self.tags.iter().any(|t| t.trim().is_empty()) {
+            return Err(Error::from(ErrorKind::ArgumentError(
+                "tag is empty".to_owned(),
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// The metadata of a transaction, with its slate id, as found by a search
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TxMetadataEntry {
+    pub tx_slate_id: String,
+    #[serde(flatten)]
+    pub metadata: TxMetadata,
+}
+
+fn load(config: &MobileWalletCfg) -> Result<HashMap<String, TxMetadata>, Error> {
+    store::load(&store::wallet_data_file(config, TX_METADATA_FILE))
+}
+
+fn update<R, F>(config: &MobileWalletCfg, f: F) -> Result<R, Error>
+where
+    F: FnOnce(&mut HashMap<String, TxMetadata>) -> R,
+{
+    store::update(&store::wallet_data_file(config, TX_METADATA_FILE), f)
+}
+
+/// The metadata of a transaction, empty if none is set
+pub fn tx_metadata(config: &MobileWalletCfg, slate_id: &str) -> Result<TxMetadata, Error> {
+    Ok(load(config)?.remove(slate_id).unwrap_or_default())
+}
+
+/// The metadata of all the transactions, by slate id
+pub fn all_tx_metadata(config: &MobileWalletCfg) -> Result<HashMap<String, TxMetadata>, Error> {
+    load(config)
+}
+
+/// Replace the metadata of a transaction, an empty metadata removing it
+pub fn set_tx_metadata(
+    config: &MobileWalletCfg,
+    slate_id: &str,
+    metadata: TxMetadata,
+) -> Result<(), Error> {
+    metadata.validate()?;
+    update(config, |all| {
+        if metadata.is_empty() {
+            all.remove(slate_id);
+        } else {
+            all.insert(slate_id.to_owned(), metadata);
+        }
+    })
+}
+
+/// Set the counterparty of a sent transaction, unless the user already set one
+pub fn set_counterparty(
+    config: &MobileWalletCfg,
+    slate_id: &str,
+    counterparty: &str,
+) -> Result<(), Error> {
+    update(config, |all| {
+        let metadata = all.entry(slate_id.to_owned()).or_default();
+        if metadata.counterparty.is_none() {
+            metadata.counterparty = Some(counterparty.to_owned());
+        }
+    })
+}
+
+/// Search the metadata whose note, counterparty or one of the tags contains the text,
+/// case insensitively.
+pub fn search_tx_metadata(
+    config: &MobileWalletCfg,
+    text: &str,
+) -> Result<Vec<TxMetadataEntry>, Error> {
+    let text = text.to_lowercase();
+    let mut entries: Vec<TxMetadataEntry> = load(config)?
+        .into_iter()
+        .filter(|(_, m)| m.matches(&text))
+        .map(|(tx_slate_id, metadata)| TxMetadataEntry {
+            tx_slate_id,
+            metadata,
+        })
+        .collect();
+    entries.sort_by(|a, b| a.tx_slate_id.cmp(&b.tx_slate_id));
+    Ok(entries)
+}
+
+/// The transactions as JSON objects, each with its metadata in the "metadata" field,
+/// which is null if none is set.
+pub fn with_tx_metadata(
+    config: &MobileWalletCfg,
+    txs: Vec<TxLogEntry>,
+) -> Result<Vec<serde_json::Value>, Error> {
+    let all = all_tx_metadata(config)?;
+    txs.into_iter()
+        .map(|tx| {
+            let metadata = tx
+                .tx_slate_id
+                .and_then(|id| all.get(&id.to_string()))
+                .map(|m| serde_json::to_value(m).unwrap());
+            let mut value = serde_json::to_value(&tx)
+                .map_err(|e| Error::from(ErrorKind::GenericError(e.to_string())))?;
+            value["metadata"] = metadata.unwrap_or(serde_json::Value::Null);
+            Ok(value)
+        })
+        .collect()
+}
@@ -0,0 +1,181 @@
+// Copyright 2019 Gary Yu.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checks of a BIP-39 recovery phrase while it's typed, before the wallet is recovered.
+//!
+//! The phrase is recovered from as it's validated, so the case and the spacing of its
+//! words don't matter.
+
+use serde::Serialize;
+
+use grin_wallet_util::grin_keychain::mnemonic::{self, WORDS};
+use grin_wallet_util::grin_util::ZeroingString;
+
+/// The valid numbers of words of a phrase
+pub const PHRASE_LENGTHS: [usize; 5] = [12, 15, 18, 21, 24];
+
+/// A word of a phrase, without the word itself so that the phrase is never copied
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WordValidity {
+    /// Position of the word in the phrase, from 0
+    pub index: usize,
+    /// The word is in the BIP-39 wordlist
+    pub valid: bool,
+}
+
+/// The validation of a phrase
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PhraseValidation {
+    /// The phrase can be used to recover a wallet
+    pub valid: bool,
+    pub words: Vec<WordValidity>,
+    /// Number of the words of the phrase
+    pub word_count: usize,
+    /// The closest valid number of words not less than the word count, or the maximum
+    pub expected_length: usize,
+    /// The checksum of the phrase, none if it's unknown because of a wrong word or length
+    pub checksum_valid: Option<bool>,
+}
+
+/// The phrase as it's validated: its words lowercased and separated by single spaces.
+///
+/// It's built in a buffer which is never reallocated, so that no copy of the phrase is
+/// left unzeroed. The wordlist is ASCII, so only the ASCII letters are lowercased.
+pub fn normalize_phrase(phrase: &str) -> ZeroingString {
+    let mut normalized = String::with_capacity(phrase.len());
+    for word in phrase.split_whitespace() {
+        if !normalized.is_empty() {
+            normalized.push(' ');
+        }
+        normalized.extend(word.chars().map(|c| c.to_ascii_lowercase()));
+    }
+    ZeroingString::from(normalized)
+}
+
+/// Validate a phrase word by word, then its length and its checksum.
+pub fn validate_phrase(phrase: &str) -> PhraseValidation {
+    let phrase = normalize_phrase(phrase);
+    let words: Vec<WordValidity> = phrase
+        .split_whitespace()
+        .enumerate()
+        .map(|(index, word)| WordValidity {
+            index,
+            valid: mnemonic::search(word).is_ok(),
+        })
+        .collect();
+    let word_count = words.len();
+    let expected_length = PHRASE_LENGTHS
+        .iter()
+        .cloned()
+        .find(|&l| l >= word_count)
+        .unwrap_or(PHRASE_LENGTHS[PHRASE_LENGTHS.len() - 1]);
+
+    let checksum_valid = if word_count == expected_length && words.iter().all(|w| w.valid) {
+        Some(mnemonic::to_entropy(&phrase).is_ok())
+    } else {
+        None
+    };
+
+    PhraseValidation {
+        valid: checksum_valid == Some(true),
+        words,
+        word_count,
+        expected_length,
+        checksum_valid,
+    }
+}
+
+/// The words of the BIP-39 wordlist starting with a prefix, in alphabetical order,
+/// none for an empty prefix.
+pub fn suggest_words(prefix: &str) -> Vec<String> {
+    let prefix = prefix.trim().to_lowercase();
+    if prefix.is_empty() {
+        return vec![];
+    }
+    WORDS
+        .iter()
+        .filter(|w| w.starts_with(&prefix))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon \
+                          abandon abandon abandon about";
+
+    #[test]
+    fn normalizes_case_and_spacing() {
+        let phrase = normalize_phrase("  Abandon\tABANDON \n abandon ");
+        assert_eq!(&*phrase, "abandon abandon abandon");
+        assert_eq!(&*normalize_phrase(" \n"), "");
+    }
+
+    #[test]
+    fn validates_a_right_phrase() {
+        let validation = validate_phrase(&PHRASE.to_uppercase());
+        assert!(validation.valid);
+        assert_eq!(validation.word_count, 12);
+        assert_eq!(validation.expected_length, 12);
+        assert_eq!(validation.checksum_valid, Some(true));
+        assert!(validation.words.iter().all(|w| w.valid));
+        let indexes: Vec<usize> = validation.words.iter().map(|w| w.index).collect();
+        assert_eq!(indexes, (0..12).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn finds_a_wrong_checksum() {
+        let validation = validate_phrase(&PHRASE.replace("about", "abandon"));
+        assert!(!validation.valid);
+        assert_eq!(validation.checksum_valid, Some(false));
+    }
+
+    #[test]
+    fn finds_the_wrong_words_and_length() {
+        let validation = validate_phrase("abandon notaword about");
+        assert!(!validation.valid);
+        assert_eq!(validation.word_count, 3);
+        assert_eq!(validation.expected_length, 12);
+        assert_eq!(validation.checksum_valid, None);
+        let wrong: Vec<usize> = validation
+            .words
+            .iter()
+            .filter(|w| !w.valid)
+            .map(|w| w.index)
+            .collect();
+        assert_eq!(wrong, vec![1]);
+
+        let validation = validate_phrase(&format!("{} abandon", PHRASE));
+        assert_eq!(validation.word_count, 13);
+        assert_eq!(validation.expected_length, 15);
+        assert_eq!(validation.checksum_valid, None);
+
+        let validation = validate_phrase(&"abandon ".repeat(25));
+        assert_eq!(validation.expected_length, 24);
+        assert_eq!(validation.checksum_valid, None);
+    }
+
+    #[test]
+    fn suggests_the_words_of_a_prefix() {
+        assert_eq!(suggest_words("zo"), vec!["zone", "zoo"]);
+        assert_eq!(suggest_words(" ABAN "), vec!["abandon"]);
+        assert!(suggest_words("").is_empty());
+        assert!(suggest_words("  ").is_empty());
+        assert!(suggest_words("xyz").is_empty());
+    }
+}
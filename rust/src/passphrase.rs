@@ -0,0 +1,190 @@
+// Copyright 2019 Gary Yu.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Wallets protected by a BIP-39 passphrase, the "25th word".
+//!
+//! The seed file only holds the entropy of the recovery phrase, as for any wallet.
+//! A passphrase given when the wallet is opened derives another keychain in memory,
+//! from the BIP-39 seed of the phrase and the passphrase, so that the same phrase with
+//! another passphrase gives another wallet. The passphrase is never stored, and a
+//! wrong one can't be told from a right one: it opens another, empty, wallet.
+//!
+//! Each of these wallets has its own directory, named by the root key id of its keychain,
+//! for its database and all its other files, so that nothing of a wallet is seen when
+//! another one is opened. The directory doesn't tell which passphrase it's for.
+
+use std::path::Path;
+
+use grin_wallet_config::WalletConfig;
+use grin_wallet_impls::{Error, ErrorKind, HTTPNodeClient, LMDBBackend, WalletSeed};
+use grin_wallet_libwallet::{
+    self as libwallet, AcctPathMapping, Context, OutputData, TxLogEntry, WalletBackend,
+    WalletOutputBatch,
+};
+use grin_wallet_util::grin_core::core::Transaction;
+use grin_wallet_util::grin_core::global::ChainTypes;
+use grin_wallet_util::grin_keychain::{mnemonic, ExtKeychain, Identifier, Keychain};
+use grin_wallet_util::grin_util::ZeroingString;
+use uuid::Uuid;
+
+/// The backend of a wallet opened with a passphrase, whose keychain is derived once
+/// when it's opened, instead of being read from the seed file by each call.
+pub struct PassphraseBackend {
+    backend: LMDBBackend<HTTPNodeClient, ExtKeychain>,
+    keychain: ExtKeychain,
+    /// The directory of the database and of the files of this wallet
+    wallet_dir: String,
+}
+
+impl PassphraseBackend {
+    /// Open the wallet of the recovery phrase of the seed file and a passphrase,
+    /// creating its database if it's opened for the first time.
+    pub fn new(
+        mut wallet_config: WalletConfig,
+        password: &str,
+        passphrase: &str,
+        node_client: HTTPNodeClient,
+    ) -> Result<Self, Error> {
+        let seed = WalletSeed::from_file(&wallet_config.data_file_dir, password)?;
+        let phrase = ZeroingString::from(seed.to_mnemonic()?);
+        let bip39_seed =
+            mnemonic::to_seed(&phrase, passphrase).map_err(|_| Error::from(ErrorKind::Mnemonic))?;
+        let is_floonet = wallet_config.chain_type == Some(ChainTypes::Floonet);
+        let keychain: ExtKeychain =
+            WalletSeed::from_bytes(&bip39_seed).derive_keychain(is_floonet)?;
+
+        let wallet_dir = Path::new(&wallet_config.data_file_dir)
+            .join(keychain.root_key_id().to_hex())
+            .to_string_lossy()
+            .into_owned();
+        wallet_config.data_file_dir = wallet_dir.clone();
+        let backend = LMDBBackend::new(wallet_config, password, node_client)?;
+        Ok(PassphraseBackend {
+            backend,
+            keychain,
+            wallet_dir,
+        })
+    }
+
+    pub fn wallet_dir(&self) -> &str {
+        &self.wallet_dir
+    }
+}
+
+impl WalletBackend<HTTPNodeClient, ExtKeychain> for PassphraseBackend {
+    fn open_with_credentials(&mut self) -> Result<(), libwallet::Error> {
+        // The password was checked when the wallet was opened, and the seed file isn't
+        // in the database directory of this wallet
+        self.backend.keychain = Some(self.keychain.clone());
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), libwallet::Error> {
+        self.backend.close()
+    }
+
+    fn keychain(&mut self) -> &mut ExtKeychain {
+        &mut self.keychain
+    }
+
+    fn w2n_client(&mut self) -> &mut HTTPNodeClient {
+        self.backend.w2n_client()
+    }
+
+    fn calc_commit_for_cache(
+        &mut self,
+        amount: u64,
+        id: &Identifier,
+    ) -> Result<Option<String>, libwallet::Error> {
+        self.backend.calc_commit_for_cache(amount, id)
+    }
+
+    fn set_parent_key_id_by_name(&mut self, label: &str) -> Result<(), libwallet::Error> {
+        self.backend.set_parent_key_id_by_name(label)
+    }
+
+    fn set_parent_key_id(&mut self, id: Identifier) {
+        self.backend.set_parent_key_id(id)
+    }
+
+    fn parent_key_id(&mut self) -> Identifier {
+        self.backend.parent_key_id()
+    }
+
+    fn iter<'a>(&'a self) -> Box<dyn Iterator<Item = OutputData> + 'a> {
+        self.backend.iter()
+    }
+
+    fn get(
+        &self,
+        id: &Identifier,
+        mmr_index: &Option<u64>,
+    ) -> Result<OutputData, libwallet::Error> {
+        self.backend.get(id, mmr_index)
+    }
+
+    fn get_tx_log_entry(&self, uuid: &Uuid) -> Result<Option<TxLogEntry>, libwallet::Error> {
+        self.backend.get_tx_log_entry(uuid)
+    }
+
+    fn get_private_context(
+        &mut self,
+        slate_id: &[u8],
+        participant_id: usize,
+    ) -> Result<Context, libwallet::Error> {
+        self.backend.get_private_context(slate_id, participant_id)
+    }
+
+    fn tx_log_iter<'a>(&'a self) -> Box<dyn Iterator<Item = TxLogEntry> + 'a> {
+        self.backend.tx_log_iter()
+    }
+
+    fn acct_path_iter<'a>(&'a self) -> Box<dyn Iterator<Item = AcctPathMapping> + 'a> {
+        self.backend.acct_path_iter()
+    }
+
+    fn get_acct_path(&self, label: String) -> Result<Option<AcctPathMapping>, libwallet::Error> {
+        self.backend.get_acct_path(label)
+    }
+
+    fn store_tx(&self, uuid: &str, tx: &Transaction) -> Result<(), libwallet::Error> {
+        self.backend.store_tx(uuid, tx)
+    }
+
+    fn get_stored_tx(&self, entry: &TxLogEntry) -> Result<Option<Transaction>, libwallet::Error> {
+        self.backend.get_stored_tx(entry)
+    }
+
+    fn batch<'a>(
+        &'a mut self,
+    ) -> Result<Box<dyn WalletOutputBatch<ExtKeychain> + 'a>, libwallet::Error> {
+        self.backend.batch()
+    }
+
+    fn next_child(&mut self) -> Result<Identifier, libwallet::Error> {
+        self.backend.next_child()
+    }
+
+    fn last_confirmed_height(&mut self) -> Result<u64, libwallet::Error> {
+        self.backend.last_confirmed_height()
+    }
+
+    fn restore(&mut self) -> Result<(), libwallet::Error> {
+        self.backend.restore()
+    }
+
+    fn check_repair(&mut self, delete_unconfirmed: bool) -> Result<(), libwallet::Error> {
+        self.backend.check_repair(delete_unconfirmed)
+    }
+}
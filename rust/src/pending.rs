@@ -0,0 +1,69 @@
+// Copyright 2019 Gary Yu.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Relay sends waiting for the payee's response.
+//!
+//! They are persisted in the wallet data directory, so that a response received
+//! after the app is restarted can still be finalized.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use grin_wallet_impls::Error;
+
+use crate::store;
+use crate::MobileWalletCfg;
+
+const PENDING_SENDS_FILE: &str = "pending_relay_sends.json";
+
+/// A relay send waiting for the payee's response
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingSend {
+    pub slate_id: String,
+    /// The relay address of the payee
+    pub addr: String,
+    pub amount: u64,
+    /// Unix timestamp of the send, in seconds
+    pub created_at: u64,
+}
+
+pub fn add_pending_send(config: &MobileWalletCfg, send: PendingSend) -> Result<(), Error> {
+    let path = store::wallet_data_file(config, PENDING_SENDS_FILE);
+    store::update(&path, |pending: &mut HashMap<String, PendingSend>| {
+        pending.insert(send.slate_id.clone(), send);
+    })
+}
+
+/// A pending send, if it exists
+pub fn pending_send(
+    config: &MobileWalletCfg,
+    slate_id: &str,
+) -> Result<Option<PendingSend>, Error> {
+    let path = store::wallet_data_file(config, PENDING_SENDS_FILE);
+    let mut pending: HashMap<String, PendingSend> = store::load(&path)?;
+    Ok(pending.remove(slate_id))
+}
+
+/// Remove a pending send, and return it if it exists.
+pub fn take_pending_send(
+    config: &MobileWalletCfg,
+    slate_id: &str,
+) -> Result<Option<PendingSend>, Error> {
+    let path = store::wallet_data_file(config, PENDING_SENDS_FILE);
+    store::update(&path, |pending: &mut HashMap<String, PendingSend>| {
+        pending.remove(slate_id)
+    })
+}
@@ -0,0 +1,371 @@
+// Copyright 2019 Gary Yu.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Payment proofs: the payee's signature over the amount, the kernel excess and
+//! the relay addresses of both sides, which the payer keeps to show the payee got paid.
+//!
+//! The slate has no field for it, so the payee returns its signature as its
+//! participant message, signed again with the blinding key of its output.
+//! The payee's address is the public key of a key derived from its seed, the payer
+//! only keeps a proof signed for the relay address it paid.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use grin_wallet_impls::{Error, ErrorKind, HTTPNodeClient};
+use grin_wallet_libwallet::{self as libwallet, NodeClient, Slate, WalletBackend, WalletInst};
+use grin_wallet_util::grin_api;
+use grin_wallet_util::grin_core::core::committed::Committed;
+use grin_wallet_util::grin_core::libtx::aggsig;
+use grin_wallet_util::grin_keychain::{ExtKeychain, Keychain, SwitchCommitmentType};
+use grin_wallet_util::grin_util::blake2::blake2b::blake2b;
+use grin_wallet_util::grin_util::secp::key::{PublicKey, SecretKey};
+use grin_wallet_util::grin_util::secp::pedersen::Commitment;
+use grin_wallet_util::grin_util::secp::{self, Message, Secp256k1, Signature};
+use grin_wallet_util::grin_util::{from_hex, static_secp_instance, to_hex};
+
+use crate::error::LibError;
+use crate::events::unix_timestamp;
+use crate::session::WalletInstance;
+use crate::store;
+use crate::MobileWalletCfg;
+
+const PAYMENT_PROOFS_FILE: &str = "payment_proofs.json";
+
+/// Derivation path of the payment proof key, out of the range of the accounts paths
+const PAYMENT_PROOF_KEY_PATH: u32 = 0x7fff_fff0;
+
+/// A payment proof of a sent transaction
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentProof {
+    pub slate_id: String,
+    pub amount: u64,
+    /// The kernel excess commitment, in hex
+    pub excess: String,
+    /// The relay address of the payer, as seen by the payee, if sent by Grin Relay
+    pub sender_address: Option<String>,
+    /// The payment proof address of the payee
+    pub receiver_address: String,
+    /// The relay address of the payee, which the payer sent the slate to
+    pub receiver_relay_address: Option<String>,
+    pub receiver_signature: String,
+    /// Unix timestamp of the finalization, in seconds
+    pub created_at: u64,
+}
+
+/// The payee's part of a payment proof, as its participant message
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ReceiverProof {
+    sender_address: Option<String>,
+    receiver_address: String,
+    receiver_relay_address: Option<String>,
+    receiver_signature: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct ProofMessage {
+    payment_proof: ReceiverProof,
+}
+
+/// The result of a payment proof verification
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProofVerification {
+    /// The kernel is on the chain
+    pub confirmed: bool,
+    pub kernel_height: Option<u64>,
+    pub confirmations: u64,
+}
+
+fn proof_error<E: std::fmt::Display>(e: E) -> Error {
+    Error::from(ErrorKind::GenericError(format!("payment proof: {}", e)))
+}
+
+/// The keychain of the wallet, which is only set between `open_with_credentials` and
+/// `close`, as for the Owner calls
+fn wallet_keychain(
+    w: &mut dyn WalletInst<HTTPNodeClient, ExtKeychain>,
+) -> Result<ExtKeychain, Error> {
+    w.open_with_credentials()?;
+    let keychain = w.keychain().clone();
+    w.close()?;
+    Ok(keychain)
+}
+
+fn proof_key(wallet: &WalletInstance) -> Result<SecretKey, Error> {
+    let keychain = wallet_keychain(&mut *wallet.lock())?;
+    let key_id = ExtKeychain::derive_key_id(3, PAYMENT_PROOF_KEY_PATH, 0, 0, 0);
+    Ok(keychain.derive_key(0, &key_id, &SwitchCommitmentType::None)?)
+}
+
+/// The payment proof address of the wallet, i.e. the public key of its proof key in hex
+pub fn proof_address(wallet: &WalletInstance) -> Result<String, Error> {
+    let key = proof_key(wallet)?;
+    let secp = static_secp_instance();
+    let secp = secp.lock();
+    let public_key = PublicKey::from_secret_key(&secp, &key).map_err(proof_error)?;
+    Ok(to_hex(public_key.serialize_vec(&secp, true).to_vec()))
+}
+
+/// The kernel excess of a slate, once all the outputs are added
+fn kernel_excess(slate: &Slate) -> Result<Commitment, Error> {
+    // Summed firstly, it locks the secp instance
+    let tx_excess = slate
+        .tx
+        .sum_commitments(slate.fee as i64)
+        .map_err(proof_error)?;
+    let secp = static_secp_instance();
+    let secp = secp.lock();
+    let offset = slate.tx.offset.secret_key(&secp).map_err(proof_error)?;
+    let offset_excess = secp.commit(0, offset).map_err(proof_error)?;
+    secp.commit_sum(vec![tx_excess], vec![offset_excess])
+        .map_err(proof_error)
+}
+
+fn proof_message(
+    amount: u64,
+    excess: &Commitment,
+    sender_address: Option<&str>,
+    receiver_relay_address: Option<&str>,
+) -> Result<Message, Error> {
+    let mut data = amount.to_be_bytes().to_vec();
+    data.extend_from_slice(&excess.0);
+    data.extend_from_slice(sender_address.unwrap_or("").as_bytes());
+    data.extend_from_slice(receiver_relay_address.unwrap_or("").as_bytes());
+    let hash = blake2b(secp::constants::MESSAGE_SIZE, &[], &data);
+    Message::from_slice(hash.as_bytes()).map_err(proof_error)
+}
+
+fn verify_signature(
+    secp: &Secp256k1,
+    message: &Message,
+    receiver_address: &str,
+    receiver_signature: &str,
+) -> Result<(), Error> {
+    let invalid = |e: String| LibError::PaymentProofInvalid.into_error(e);
+    let public_key = from_hex(receiver_address.to_owned())
+        .map_err(|e| invalid(format!("bad receiver address: {}", e)))
+        .and_then(|k| {
+            PublicKey::from_slice(secp, &k)
+                .map_err(|e| invalid(format!("bad receiver address: {}", e)))
+        })?;
+    let signature = from_hex(receiver_signature.to_owned())
+        .map_err(|e| invalid(format!("bad receiver signature: {}", e)))
+        .and_then(|s| {
+            Signature::from_compact(secp, &s)
+                .map_err(|e| invalid(format!("bad receiver signature: {}", e)))
+        })?;
+    secp.verify(message, &signature, &public_key)
+        .map_err(|e| invalid(format!("wrong receiver signature: {}", e)))
+}
+
+/// As the payee, sign a payment proof of a received slate into our participant message,
+/// for our relay address the slate was received at.
+///
+/// Nothing is added if the slate already has a message of ours, or if our output
+/// can't be found.
+pub fn add_payment_proof(
+    wallet: &WalletInstance,
+    slate: &mut Slate,
+    sender_address: Option<&str>,
+    receiver_relay_address: Option<&str>,
+) -> Result<(), Error> {
+    let index = match slate.participant_data.iter().position(|p| p.id == 1) {
+        Some(index) if slate.participant_data[index].message.is_none() => index,
+        _ => return Ok(()),
+    };
+
+    // Our output, whose blinding key is our blind excess
+    let commits: Vec<String> = slate
+        .tx
+        .outputs()
+        .iter()
+        .map(|o| to_hex(o.commit.0.to_vec()))
+        .collect();
+    let (keychain, outputs) = {
+        let mut w = wallet.lock();
+        let keychain = wallet_keychain(&mut *w)?;
+        let outputs: Vec<_> = w
+            .iter()
+            .filter(|o| o.commit.as_ref().map_or(false, |c| commits.contains(c)))
+            .collect();
+        (keychain, outputs)
+    };
+    if outputs.len() != 1 {
+        return Ok(());
+    }
+    let blind_key = keychain.derive_key(
+        outputs[0].value,
+        &outputs[0].key_id,
+        &SwitchCommitmentType::Regular,
+    )?;
+
+    let excess = kernel_excess(slate)?;
+    let message = proof_message(
+        slate.amount,
+        &excess,
+        sender_address,
+        receiver_relay_address,
+    )?;
+    let key = proof_key(wallet)?;
+    let receiver_address = proof_address(wallet)?;
+
+    let secp = static_secp_instance();
+    let secp = secp.lock();
+    let blind_excess = PublicKey::from_secret_key(&secp, &blind_key).map_err(proof_error)?;
+    if blind_excess != slate.participant_data[index].public_blind_excess {
+        return Ok(());
+    }
+    let signature = secp.sign(&message, &key).map_err(proof_error)?;
+    let proof_message = serde_json::to_string(&ProofMessage {
+        payment_proof: ReceiverProof {
+            sender_address: sender_address.map(|a| a.to_owned()),
+            receiver_address,
+            receiver_relay_address: receiver_relay_address.map(|a| a.to_owned()),
+            receiver_signature: to_hex(signature.serialize_compact(&secp).to_vec()),
+        },
+    })
+    .map_err(proof_error)?;
+
+    // Signed like the participant messages, so that the payer can verify it
+    let hash = blake2b(secp::constants::MESSAGE_SIZE, &[], proof_message.as_bytes());
+    let m = Message::from_slice(hash.as_bytes()).map_err(proof_error)?;
+    let message_sig = aggsig::sign_single(&secp, &m, &blind_key, None, Some(&blind_excess))
+        .map_err(proof_error)?;
+    let participant = &mut slate.participant_data[index];
+    participant.message = Some(proof_message);
+    participant.message_sig = Some(message_sig);
+    Ok(())
+}
+
+/// As the payer, verify and save the payment proof of a finalized slate, which was
+/// sent to the relay address `recipient`.
+///
+/// Returns `None` if the payee didn't send a payment proof.
+pub fn save_payment_proof(
+    config: &MobileWalletCfg,
+    slate: &Slate,
+    recipient: &str,
+) -> Result<Option<PaymentProof>, Error> {
+    let receiver_proof = slate
+        .participant_data
+        .iter()
+        .find(|p| p.id == 1)
+        .and_then(|p| p.message.as_ref())
+        .and_then(|m| serde_json::from_str::<ProofMessage>(m).ok())
+        .map(|m| m.payment_proof);
+    let receiver_proof = match receiver_proof {
+        Some(receiver_proof) => receiver_proof,
+        None => return Ok(None),
+    };
+    // Otherwise the proof doesn't show who got paid
+    let signed_for = receiver_proof.receiver_relay_address.as_ref();
+    if signed_for.map(|a| a.as_str()) != Some(recipient) {
+        return Err(LibError::PaymentProofInvalid.into_error(format!(
+            "payment proof is signed for {}, not for {}",
+            signed_for.map_or("no relay address", |a| a.as_str()),
+            recipient
+        )));
+    }
+    let excess = slate
+        .tx
+        .kernels()
+        .first()
+        .map(|k| k.excess)
+        .ok_or_else(|| proof_error("transaction has no kernel"))?;
+
+    let proof = PaymentProof {
+        slate_id: slate.id.to_string(),
+        amount: slate.amount,
+        excess: to_hex(excess.0.to_vec()),
+        sender_address: receiver_proof.sender_address,
+        receiver_address: receiver_proof.receiver_address,
+        receiver_relay_address: receiver_proof.receiver_relay_address,
+        receiver_signature: receiver_proof.receiver_signature,
+        created_at: unix_timestamp(),
+    };
+    check_signature(&proof)?;
+
+    let path = store::wallet_data_file(config, PAYMENT_PROOFS_FILE);
+    store::update(&path, |proofs: &mut HashMap<String, PaymentProof>| {
+        proofs.insert(proof.slate_id.clone(), proof.clone());
+    })?;
+    Ok(Some(proof))
+}
+
+/// The payment proof of a sent transaction
+pub fn payment_proof(config: &MobileWalletCfg, slate_id: &str) -> Result<PaymentProof, Error> {
+    let path = store::wallet_data_file(config, PAYMENT_PROOFS_FILE);
+    let mut proofs: HashMap<String, PaymentProof> = store::load(&path)?;
+    proofs.remove(slate_id).ok_or_else(|| {
+        LibError::PaymentProofNotFound.into_error(format!("no payment proof for tx {}", slate_id))
+    })
+}
+
+fn check_signature(proof: &PaymentProof) -> Result<(), Error> {
+    let invalid = |e: String| LibError::PaymentProofInvalid.into_error(e);
+    let excess = from_hex(proof.excess.clone())
+        .map(Commitment::from_vec)
+        .map_err(|e| invalid(format!("bad excess: {}", e)))?;
+    let message = proof_message(
+        proof.amount,
+        &excess,
+        proof.sender_address.as_ref().map(|a| a.as_str()),
+        proof.receiver_relay_address.as_ref().map(|a| a.as_str()),
+    )?;
+
+    let secp = static_secp_instance();
+    let secp = secp.lock();
+    verify_signature(
+        &secp,
+        &message,
+        &proof.receiver_address,
+        &proof.receiver_signature,
+    )
+}
+
+/// Verify the signature of a payment proof, then look for its kernel on the chain.
+///
+/// A failed lookup is returned as a node error, it doesn't mean the kernel isn't there.
+pub fn verify_payment_proof(
+    wallet: &WalletInstance,
+    proof: &PaymentProof,
+) -> Result<ProofVerification, Error> {
+    check_signature(proof)?;
+
+    let (height, url, api_secret) = {
+        let mut w = wallet.lock();
+        let client = w.w2n_client();
+        let height = client.get_chain_height()?;
+        let url = format!("{}/v1/chain/kernels/{}", client.node_url(), proof.excess);
+        (height, url, client.node_api_secret())
+    };
+    let kernel = grin_api::client::get::<serde_json::Value>(&url, api_secret).map_err(|e| {
+        libwallet::Error::from(libwallet::ErrorKind::ClientCallback(format!(
+            "fail to get kernel {}: {}",
+            proof.excess, e
+        )))
+    })?;
+    // The node returns null for a kernel which isn't on the chain
+    let kernel_height = kernel["height"].as_u64();
+    Ok(ProofVerification {
+        confirmed: kernel_height.is_some(),
+        kernel_height,
+        confirmations: kernel_height.map_or(0, |h| height.saturating_sub(h) + 1),
+    })
+}
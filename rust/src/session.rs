@@ -0,0 +1,359 @@
+// Copyright 2019 Gary Yu.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Persistent wallet sessions, so that the seed and the LMDB backend are
+//! opened once and shared by all the calls on the same handle.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use grin_wallet_api::Owner;
+use grin_wallet_impls::{Error, ErrorKind, HTTPNodeClient, LMDBBackend};
+use grin_wallet_libwallet::{WalletBackend, WalletInst};
+use grin_wallet_util::grin_keychain::ExtKeychain;
+use grin_wallet_util::grin_util::{file::get_first_line, Mutex, RwLock, ZeroingString};
+
+use crate::attempts::{is_wiped, save_wipe_policy, verify_password};
+use crate::coins::restore_excluded_outputs;
+use crate::error::LibError;
+use crate::listener::{restart_listeners, stop_listeners};
+use crate::passphrase::PassphraseBackend;
+use crate::{new_wallet_config, MobileWalletCfg};
+
+/// The wallet instance type shared by a session
+pub type WalletInstance = Arc<Mutex<dyn WalletInst<HTTPNodeClient, ExtKeychain>>>;
+
+/// Source of the session ids, unique for the library lifetime
+static NEXT_SESSION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// An opened wallet, with the config it was opened with
+pub struct WalletSession {
+    /// Identifies the opened wallet instance, unlike the data directory which is shared
+    /// by the wallets of all the passphrases
+    id: u64,
+    pub config: MobileWalletCfg,
+    pub wallet: WalletInstance,
+    /// The BIP-39 passphrase the wallet was opened with, to open it again
+    passphrase: Option<ZeroingString>,
+    /// Held for writing while a call temporarily switches the active account,
+    /// and for reading by the other calls and by the listener.
+    account_lock: Arc<RwLock<()>>,
+    lock_state: Mutex<LockState>,
+}
+
+/// What a call does, to decide whether it can run on a locked session
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Access {
+    /// Only reads the wallet, allowed on a locked session if the config says so
+    ReadOnly,
+    /// Spends, reveals secrets or changes the wallet, never allowed on a locked session
+    Full,
+}
+
+struct LockState {
+    locked: bool,
+    last_activity: Instant,
+}
+
+impl WalletSession {
+    /// Open the wallet described by the config, without registering it.
+    ///
+    /// The password is only used to open the wallet, and is never kept. With a passphrase,
+    /// it's the wallet of the recovery phrase and this passphrase, see `PassphraseBackend`.
+    pub fn open(
+        mut config: MobileWalletCfg,
+        password: &ZeroingString,
+        passphrase: Option<&str>,
+    ) -> Result<Self, Error> {
+        let wallet = open_wallet(&mut config, password, passphrase)?;
+        restore_excluded_outputs(&config, &wallet)?;
+        Ok(WalletSession {
+            id: NEXT_SESSION_ID.fetch_add(1, Ordering::Relaxed),
+            config,
+            wallet,
+            passphrase: passphrase.map(ZeroingString::from),
+            account_lock: Arc::new(RwLock::new(())),
+            lock_state: Mutex::new(LockState {
+                locked: false,
+                last_activity: Instant::now(),
+            }),
+        })
+    }
+
+    pub fn from_json(json_cfg: &str, password: &ZeroingString) -> Result<Self, Error> {
+        WalletSession::open(MobileWalletCfg::from_str(json_cfg)?, password, None)
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The lock of the active account, see `with_session`
+    pub fn account_lock(&self) -> Arc<RwLock<()>> {
+        self.account_lock.clone()
+    }
+
+    /// Lock the session if it's idle for longer than the configured timeout,
+    /// and return whether it's locked.
+    fn check_idle(&self, state: &mut LockState) -> bool {
+        if let Some(timeout) = self.config.lock_timeout_secs {
+            if state.last_activity.elapsed() >= Duration::from_secs(timeout) {
+                state.locked = true;
+            }
+        }
+        state.locked
+    }
+
+    /// Check that a call can run on this session, and record the activity.
+    ///
+    /// Only the full access calls count as activity, so that an app polling the
+    /// balance or the transactions doesn't keep an idle session unlocked.
+    pub fn check_access(&self, access: Access) -> Result<(), Error> {
+        if is_wiped(&self.config.data_dir) {
+            return Err(LibError::WalletWiped.into_error("wallet data wiped".to_owned()));
+        }
+        let mut state = self.lock_state.lock();
+        if self.check_idle(&mut state) {
+            if access == Access::ReadOnly && self.config.read_when_locked {
+                return Ok(());
+            }
+            return Err(LibError::WalletLocked.into_error("wallet session is locked".to_owned()));
+        }
+        if access == Access::Full {
+            state.last_activity = Instant::now();
+        }
+        Ok(())
+    }
+
+    pub fn is_locked(&self) -> bool {
+        let mut state = self.lock_state.lock();
+        self.check_idle(&mut state)
+    }
+
+    pub fn lock(&self) {
+        self.lock_state.lock().locked = true;
+    }
+
+    /// Unlock the session, if the password is the wallet one
+    pub fn unlock(&self, password: &ZeroingString) -> Result<(), Error> {
+        verify_password(&self.config, password)?;
+        let mut state = self.lock_state.lock();
+        state.locked = false;
+        state.last_activity = Instant::now();
+        Ok(())
+    }
+}
+
+/// Open the wallet, and set the directory of its files in the config if it's the wallet
+/// of a passphrase.
+fn open_wallet(
+    config: &mut MobileWalletCfg,
+    password: &str,
+    passphrase: Option<&str>,
+) -> Result<WalletInstance, Error> {
+    let wallet_config = new_wallet_config(config.clone())?;
+    let node_api_secret = get_first_line(wallet_config.node_api_secret_path.clone());
+    let node_client = HTTPNodeClient::new(&wallet_config.check_node_api_http_addr, node_api_secret);
+
+    // Test the password firstly, so we can abort early if it's wrong
+    verify_password(config, password)?;
+    match passphrase {
+        Some(passphrase) => {
+            let mut db_wallet =
+                PassphraseBackend::new(wallet_config, password, passphrase, node_client)?;
+            db_wallet.set_parent_key_id_by_name(config.account.as_str())?;
+            config.wallet_dir = Some(db_wallet.wallet_dir().to_owned());
+            Ok(Arc::new(Mutex::new(db_wallet)))
+        }
+        None => {
+            let mut db_wallet: LMDBBackend<HTTPNodeClient, ExtKeychain> =
+                LMDBBackend::new(wallet_config, password, node_client)?;
+            db_wallet.set_parent_key_id_by_name(config.account.as_str())?;
+            Ok(Arc::new(Mutex::new(db_wallet)))
+        }
+    }
+}
+
+struct Registry {
+    next_handle: u64,
+    sessions: HashMap<u64, Arc<WalletSession>>,
+}
+
+lazy_static! {
+    static ref REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+        next_handle: 1,
+        sessions: HashMap::new(),
+    });
+}
+
+/// Open a wallet session and return its handle.
+///
+/// A wallet can only be opened once: if a session on the same data directory is
+/// already open, the password and the passphrase are checked and its handle is returned.
+/// The session account stays the one it was opened with, the other accounts are
+/// selected per call.
+pub fn open_session(
+    config: MobileWalletCfg,
+    password: &ZeroingString,
+    passphrase: Option<&str>,
+) -> Result<u64, Error> {
+    // The registry stays locked while opening, so that concurrent opens of the same
+    // wallet never open its LMDB backend twice.
+    let mut registry = REGISTRY.lock();
+    let existing = registry
+        .sessions
+        .iter()
+        .find(|(_, s)| s.config.data_dir == config.data_dir);
+    if let Some((handle, session)) = existing {
+        verify_password(&config, password)?;
+        if session.passphrase.as_ref().map(|p| p.as_str()) != passphrase {
+            return Err(Error::from(ErrorKind::ArgumentError(
+                "the wallet is open with another passphrase, close it firstly".to_owned(),
+            )));
+        }
+        save_wipe_policy(&config)?;
+        return Ok(*handle);
+    }
+
+    let session = WalletSession::open(config, password, passphrase)?;
+    save_wipe_policy(&session.config)?;
+    let handle = registry.next_handle;
+    registry.next_handle += 1;
+    registry.sessions.insert(handle, Arc::new(session));
+    Ok(handle)
+}
+
+/// Close a wallet session, and stop the listeners of its wallet. The wallet is released
+/// once the calls in flight are finished.
+pub fn close_session(handle: u64) -> Result<(), Error> {
+    let session = REGISTRY
+        .lock()
+        .sessions
+        .remove(&handle)
+        .ok_or_else(|| unknown_handle(handle))?;
+    stop_listeners(session.id());
+    Ok(())
+}
+
+/// Get the session of a handle
+pub fn get_session(handle: u64) -> Result<Arc<WalletSession>, Error> {
+    REGISTRY
+        .lock()
+        .sessions
+        .get(&handle)
+        .cloned()
+        .ok_or_else(|| unknown_handle(handle))
+}
+
+/// Run a call on the session of a handle, which fails if the session is locked.
+///
+/// If an account other than the session one is given, it's the active account for
+/// the duration of the call, and the other calls on this session wait for it.
+pub fn with_session<T, F>(handle: u64, account: Option<&str>, f: F) -> Result<T, Error>
+where
+    F: FnOnce(&WalletSession) -> Result<T, Error>,
+{
+    with_session_access(handle, account, Access::Full, f)
+}
+
+/// Run a read-only call on the session of a handle, see `with_session`.
+pub fn with_session_read<T, F>(handle: u64, account: Option<&str>, f: F) -> Result<T, Error>
+where
+    F: FnOnce(&WalletSession) -> Result<T, Error>,
+{
+    with_session_access(handle, account, Access::ReadOnly, f)
+}
+
+fn with_session_access<T, F>(
+    handle: u64,
+    account: Option<&str>,
+    access: Access,
+    f: F,
+) -> Result<T, Error>
+where
+    F: FnOnce(&WalletSession) -> Result<T, Error>,
+{
+    let session = get_session(handle)?;
+    session.check_access(access)?;
+    match account {
+        Some(account) if account != session.config.account => {
+            let _guard = session.account_lock.write();
+            Owner::new(session.wallet.clone()).set_active_account(account)?;
+            let _restore = RestoreAccount { session: &session };
+            f(&session)
+        }
+        _ => {
+            let _guard = session.account_lock.read();
+            f(&session)
+        }
+    }
+}
+
+/// Restore the session account when dropped, even if the call panics.
+struct RestoreAccount<'a> {
+    session: &'a WalletSession,
+}
+
+impl<'a> Drop for RestoreAccount<'a> {
+    fn drop(&mut self) {
+        let _ = Owner::new(self.session.wallet.clone())
+            .set_active_account(&self.session.config.account);
+    }
+}
+
+/// Re-open the wallet of a handle, i.e. with its new password after a password change.
+///
+/// The listeners of the old session are stopped, since they hold its wallet, and started
+/// again for the new one. If the wallet can't be opened again, the handle is closed as
+/// by `close_session`.
+pub fn reopen_session(
+    handle: u64,
+    config: MobileWalletCfg,
+    password: &ZeroingString,
+) -> Result<(), Error> {
+    let mut registry = REGISTRY.lock();
+    // The old backend must be released before the wallet is opened again
+    let (old_id, passphrase) = match registry.sessions.remove(&handle) {
+        Some(session) => (
+            session.id(),
+            session
+                .passphrase
+                .as_ref()
+                .map(|p| ZeroingString::from(p.as_str())),
+        ),
+        None => return Err(unknown_handle(handle)),
+    };
+    let passphrase = passphrase.as_ref().map(|p| p.as_str());
+    let listener_ids = stop_listeners(old_id);
+    match WalletSession::open(config, password, passphrase) {
+        Ok(session) => {
+            let session = Arc::new(session);
+            registry.sessions.insert(handle, session.clone());
+            restart_listeners(&listener_ids, &session)
+        }
+        Err(e) => Err(LibError::SessionClosed.into_error(format!(
+            "wallet handle {} is closed, it can't be re-opened: {}",
+            handle, e
+        ))),
+    }
+}
+
+fn unknown_handle(handle: u64) -> Error {
+    Error::from(ErrorKind::ArgumentError(format!(
+        "unknown wallet handle: {}",
+        handle
+    )))
+}
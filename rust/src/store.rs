@@ -0,0 +1,93 @@
+// Copyright 2019 Gary Yu.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Small JSON files kept in the wallet data directory, for the local states
+//! which are not part of the wallet database.
+//!
+//! The files of a wallet opened with a passphrase are kept in its own directory,
+//! with its database, see `PassphraseBackend`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use grin_wallet_impls::{Error, ErrorKind};
+use grin_wallet_util::grin_util::Mutex;
+
+use crate::error::LibError;
+use crate::MobileWalletCfg;
+
+lazy_static! {
+    // Serialize the read-modify-write of the files
+    static ref UPDATE_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// Path of the wallet data directory, i.e. the seed, the database and the local states
+pub fn wallet_data_dir(config: &MobileWalletCfg) -> PathBuf {
+    Path::new(&config.data_dir).join("wallet_data")
+}
+
+/// Path of a file in the wallet data directory
+/// A file of the opened wallet, in its own directory if it's opened with a passphrase
+pub fn wallet_data_file(config: &MobileWalletCfg, name: &str) -> PathBuf {
+    match config.wallet_dir {
+        Some(ref wallet_dir) => Path::new(wallet_dir).join(name),
+        None => wallet_data_dir(config).join(name),
+    }
+}
+
+/// Load a JSON file, or the default value if the file doesn't exist yet.
+pub fn load<T: DeserializeOwned + Default>(path: &Path) -> Result<T, Error> {
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let content = fs::read_to_string(path)
+        .map_err(|e| LibError::Io.into_error(format!("fail to read {}: {}", path.display(), e)))?;
+    serde_json::from_str(&content).map_err(|e| {
+        Error::from(ErrorKind::GenericError(format!(
+            "fail to parse {}: {}",
+            path.display(),
+            e
+        )))
+    })
+}
+
+/// Save a JSON file. It's written to a temporary file firstly then renamed,
+/// so that a crash never leaves a truncated file.
+pub fn save<T: Serialize>(path: &Path, value: &T) -> Result<(), Error> {
+    let content = serde_json::to_string_pretty(value)
+        .map_err(|e| Error::from(ErrorKind::GenericError(e.to_string())))?;
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, content)
+        .and_then(|_| fs::rename(&tmp_path, path))
+        .map_err(|e| LibError::Io.into_error(format!("fail to write {}: {}", path.display(), e)))
+}
+
+/// Update a JSON file by a function of its value, and return the result of the function.
+///
+/// The updates are serialized, while the reads need no lock since `save` replaces
+/// a file at once.
+pub fn update<T, R, F>(path: &Path, f: F) -> Result<R, Error>
+where
+    T: Serialize + DeserializeOwned + Default,
+    F: FnOnce(&mut T) -> R,
+{
+    let _guard = UPDATE_LOCK.lock();
+    let mut value = load(path)?;
+    let res = f(&mut value);
+    save(path, &value)?;
+    Ok(res)
+}